@@ -95,6 +95,18 @@ def plot(group: Group, ax: plt.Axes, colour: list[float], name: int, label: str,
     linestyle, alpha = ('--', 0.5) if dp.lattice_type == 'regular' else ('-', 1)
     ax.plot(dp.hs, dp.ms, linestyle=linestyle, color=(*colour, alpha), label=f"""{dp.lattice_type}""")
 
+    if 'theory_path' in dp.desc:
+      df = pd.read_csv(dp.desc['theory_path'])
+
+      ax.plot(df['H'], df['M_mf'], linestyle='dotted', color=(*colour, alpha / 2), label=f"""{dp.lattice_type} mean field""")
+      ax.plot(df['H'], df['M_bethe'], linestyle='dashdot', color=(*colour, alpha / 2), label=f"""{dp.lattice_type} Bethe""")
+
+    if 'metrics' in dp.desc:
+      metrics = {k: (np.nan if v is None else v) for k, v in dp.desc['metrics'].items()}
+
+      ax.scatter([metrics['coercive_down'], metrics['coercive_up']], [0, 0], marker='x', color=(*colour, alpha))
+      ax.scatter([0, 0], [metrics['remanence_down'], metrics['remanence_up']], marker='o', color=(*colour, alpha))
+
 def plot_energy(group: Group, ax: plt.Axes, colour: list[float], name: int, label: str):
   size = float(group.data[0].desc['config']['size'])**2
   max_t = max([t for dp in group.data for t in dp.ts])
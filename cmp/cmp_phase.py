@@ -19,6 +19,7 @@ from typing import TypeVar, Generic, Union, Any
 class DataPoint:
   ts: list[float]
   ms: list[float]
+  errs: list[float]
   deg_avg: float
   deg_mse: float
   seed: int
@@ -82,6 +83,34 @@ def bin_up(xs: list[Union[float, tuple[float, float]]], bins=10, mn=None, mx=Non
 
   return boundaries
 
+def bootstrap(xs: list[float], resamples=1000, rng=np.random.default_rng(0)) -> float:
+  xs = np.asarray(xs)
+
+  if len(xs) < 2:
+    return np.nan
+
+  means = rng.choice(xs, size=(resamples, len(xs))).mean(axis=1)
+
+  return means.std(ddof=1)
+
+def seed_average(data: list[DataPoint]) -> tuple[list[float], list[float], list[float], list[list[float]]]:
+  """
+  Mean of M over the seeds at every T, with its bootstrap error; a T only one
+  seed reached keeps that run's own error
+  """
+  by_t: dict[float, list[tuple[float, float]]] = {}
+
+  for dp in data:
+    for t, m, e in zip(dp.ts, dp.ms, dp.errs):
+      by_t.setdefault(round(t, 9), []).append((m, e))
+
+  ts = sorted(by_t)
+  mss = [[m for m, _ in by_t[t]] for t in ts]
+  means = [np.mean(ms) for ms in mss]
+  errs = [bootstrap(ms) if len(ms) > 1 else by_t[t][0][1] for t, ms in zip(ts, mss)]
+
+  return ts, means, errs, mss
+
 def ext_avg(xs, length=None):
   m, s, M = reduce(
     lambda u, x: (min(u[0], x), u[1] + x, max(u[2], x)), 
@@ -133,40 +162,22 @@ Plot the main phase transition diagram
 """
 def plot_main(group: Group, ax: plt.Axes, colour: list[float], name: str, bounds: tuple[float]):
   data = group.data
-  ts, mss = [], []
-  min_curve, max_curve = [], []
-
-  # group data
-  for dp in data:
-    ts = dp.ts if len(dp.ts) > len(ts) else ts
-    mss.append(dp.ms)
-
-  # process data
-  for i in range(0, max(len(ms) for ms in mss)):
-    mms = [ms[i] for ms in mss if i < len(ms)]
-
-    inf, m_avg, sup = ext_avg(mms)
-
-    max_curve.append(sup)
-    if len(mms) == len(data):
-      min_curve.append(inf)
+  ts, ms, errs, mss = seed_average(data)
 
   # set labels
   ax.set_xlabel('$T$')
   ax.set_ylabel('$M$')
 
-  # fin the average curve
-  ts_between, ms_between = fit_in_between(ts, min_curve, max_curve)
-
   # plot the extrema curves
-  ax.scatter(ts[:len(min_curve)], min_curve, color=(*colour, 0.05), marker='.')
-  ax.scatter(ts, max_curve, color=(*colour, 0.05), marker='.')
+  ax.scatter(ts, [min(m) for m in mss], color=(*colour, 0.05), marker='.')
+  ax.scatter(ts, [max(m) for m in mss], color=(*colour, 0.05), marker='.')
 
-  # plot the average curve
-  ax.scatter(ts_between, ms_between, color=(*colour, 0.9), marker='.')
+  # plot the average curve with its error
+  ax.errorbar(ts, ms, yerr=errs, color=(*colour, 0.9), fmt='.', capsize=2,
+    label=f'[{group.label}] mean over {len(data)} seeds')
 
   # plot the fitted line
-  ts_fit, ms_fit, fit_params = fit_plot(ts_between, ms_between, bounds)
+  ts_fit, ms_fit, fit_params = fit_plot(ts, ms, bounds)
   m0, tc, beta = fit_params
 
   ax.plot(ts_fit, ms_fit, linestyle='dashed',
@@ -181,6 +192,14 @@ def plot_main(group: Group, ax: plt.Axes, colour: list[float], name: str, bounds
     linestyle='--',
   )
 
+  # plot the mean-field and Bethe curves of the first network in the group
+  theory_path = data[0].desc.get('theory_path') if len(data) > 0 else None
+  if theory_path is not None:
+    df = pd.read_csv(theory_path)
+
+    ax.plot(df['T'], df['M_mf'], linestyle='dotted', color=(*colour, 0.6), label=f'[{group.label}] mean field')
+    ax.plot(df['T'], df['M_bethe'], linestyle='dashdot', color=(*colour, 0.6), label=f'[{group.label}] Bethe')
+
 """
 Plot energy of time
 """
@@ -374,15 +393,17 @@ def main(paths: list[str]):
     'irregular': ParamRegister()
   }
 
-  # make groups
+  # make groups, by the network the descriptor records or else by the path
   print(f"[process] processing desc.json paths ({len(paths)} paths)")
   for path in paths:
-    for group in groups:
-      if re.search(group.pattern, path) != None:
-        f = open(path, 'r')
-        desc = json.loads(f.read())
-        f.close()
+    f = open(path, 'r')
+    desc = json.loads(f.read())
+    f.close()
+
+    network = desc.get('network_type', '').lower()
 
+    for group in groups:
+      if network == group.label or (network == '' and re.search(group.pattern, path) != None):
         group.paths.append(desc['data_path'])
         group.descs.append(desc)
         break
@@ -397,9 +418,13 @@ def main(paths: list[str]):
 
       size2 = desc['config']['size']**2
 
+      # ⟨|M|⟩ and its error where the run has them, else the last M
+      has_abs = 'absM' in df and 'absM_err' in df
+
       group.data.append(DataPoint(
         ts=df['T'], 
-        ms=df['M'], 
+        ms=df['absM'] if has_abs else df['M'], 
+        errs=df['absM_err'] if has_abs else [np.nan] * len(df),
         deg_avg=desc['deg_avg'], 
         deg_mse=desc['deg_mse'], 
         seed=desc['seed'], 
@@ -0,0 +1,167 @@
+use std::{error::Error, fs::File, io, io::Write, path::Path, str::FromStr};
+
+use csv::{Reader, Writer};
+use rand_chacha::ChaCha20Rng;
+use serde::Serialize;
+
+use crate::{
+    child::{send, ChildMsg},
+    matrix::Matrix,
+    simulation::Simulation,
+};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum ScheduleKind {
+    Linear,
+    Exponential,
+    Logarithmic,
+    Table,
+}
+
+impl FromStr for ScheduleKind {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" | "lin" => Ok(ScheduleKind::Linear),
+            "exponential" | "exp" => Ok(ScheduleKind::Exponential),
+            "logarithmic" | "log" => Ok(ScheduleKind::Logarithmic),
+            "table" => Ok(ScheduleKind::Table),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown schedule {:?}, expected linear, exponential, logarithmic or table", s),
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Schedule {
+    pub temps: Vec<f64>,
+    pub sweeps: usize,
+}
+
+impl Schedule {
+    pub fn new(kind: ScheduleKind, t_start: f64, t_end: f64, stages: usize, sweeps: usize) -> Self {
+        let last = stages.saturating_sub(1).max(1) as f64;
+
+        let temps = (0..stages)
+            .map(|k| {
+                let x = k as f64 / last;
+
+                match kind {
+                    ScheduleKind::Exponential => t_start * (t_end / t_start).powf(x),
+                    // T_k = T_0 / (1 + c ln(1 + k)), with c picked so that the last stage hits t_end
+                    ScheduleKind::Logarithmic => {
+                        let c = (t_start / t_end - 1.) / (1. + last).ln();
+
+                        t_start / (1. + c * (1. + k as f64).ln())
+                    }
+                    _ => t_start + (t_end - t_start) * x,
+                }
+            })
+            .collect();
+
+        Schedule { temps, sweeps }
+    }
+
+    /// Reads one temperature per stage from the `T` column of a csv file.
+    pub fn from_table(path: &Path, sweeps: usize) -> Result<Self, Box<dyn Error>> {
+        let mut reader = Reader::from_path(path)?;
+        let column = reader
+            .headers()?
+            .iter()
+            .position(|h| h == "T")
+            .ok_or_else(|| format!("schedule table {} has no T column", path.display()))?;
+
+        let mut temps = vec![];
+        for record in reader.records() {
+            temps.push(record?[column].trim().parse::<f64>()?);
+        }
+
+        Ok(Schedule { temps, sweeps })
+    }
+}
+
+#[derive(Debug)]
+pub struct Annealing {
+    pub best_spins: Matrix<i8>,
+    pub best_energy: f64,
+    pub best_time: u128,
+}
+
+impl Annealing {
+    pub fn new(s: &mut Simulation) -> Self {
+        s.refresh_measurements();
+
+        Annealing {
+            best_spins: s.network.spins.clone(),
+            best_energy: s.ham(),
+            best_time: s.time,
+        }
+    }
+
+    pub fn simulate(
+        &mut self,
+        s: &mut Simulation,
+        data_dist_path: &Path,
+        schedule: &Schedule,
+        rand: &mut ChaCha20Rng,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut data_writer = Writer::from_path(data_dist_path)?;
+        // Write header
+        data_writer.write_record(["t", "T", "M", "E"])?;
+
+        for (stage, &temp) in schedule.temps.iter().enumerate() {
+            s.set_temp(temp);
+
+            for _ in 0..schedule.sweeps {
+                s.mc_iter(rand);
+                s.time += 1;
+
+                let e = s.ham();
+                if e < self.best_energy {
+                    self.best_energy = e;
+                    self.best_time = s.time;
+                    self.best_spins = s.network.spins.clone();
+                }
+
+                data_writer.serialize((s.time, temp, s.mag(), e))?;
+            }
+
+            send!(
+                s.tx,
+                s.name,
+                format!(
+                    "stage: {}/{}, T: {}, E: {}, E_min: {} (t: {})",
+                    stage + 1,
+                    schedule.temps.len(),
+                    temp,
+                    s.ham(),
+                    self.best_energy,
+                    self.best_time
+                )
+            );
+        }
+
+        data_writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn save_best(&self, s: &Simulation, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut f = File::create(path)?;
+
+        f.write_all(serde_json::to_string(&self.best_spins)?.as_bytes())?;
+        f.flush()?;
+
+        let mut network = s.network.clone();
+        network.spins = self.best_spins.clone();
+        network.plot_spins(
+            &format!("{}.png", path.trim_end_matches(".json")),
+            &format!("E_min: {}, t: {}", self.best_energy, self.best_time),
+        )?;
+
+        Ok(())
+    }
+}
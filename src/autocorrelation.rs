@@ -0,0 +1,55 @@
+use rustfft::{num_complex::Complex, FftPlanner};
+
+/// Window factor of the Sokal self-consistent cut-off, W ≥ C τ_int(W).
+const SOKAL_C: f64 = 5.;
+
+/// Normalised autocorrelation ρ(t) for t < n, through a zero padded FFT.
+pub fn autocorrelation(series: &[f64]) -> Vec<f64> {
+    let n = series.len();
+    if n == 0 {
+        return vec![];
+    }
+
+    let mean = series.iter().sum::<f64>() / n as f64;
+    let len = (2 * n).next_power_of_two();
+    let mut planner = FftPlanner::new();
+
+    let mut buffer: Vec<Complex<f64>> = series
+        .iter()
+        .map(|&x| Complex::new(x - mean, 0.))
+        .chain(std::iter::repeat(Complex::new(0., 0.)))
+        .take(len)
+        .collect();
+
+    planner.plan_fft_forward(len).process(&mut buffer);
+    buffer.iter_mut().for_each(|c| *c = Complex::new(c.norm_sqr(), 0.));
+    planner.plan_fft_inverse(len).process(&mut buffer);
+
+    let c0 = buffer[0].re;
+    if c0 <= 0. {
+        return vec![0.; n];
+    }
+
+    // C(t) is summed over n - t pairs
+    (0..n)
+        .map(|t| buffer[t].re / (n - t) as f64 / (c0 / n as f64))
+        .collect()
+}
+
+/// Integrated autocorrelation time τ_int = 1/2 + Σ_{t=1}^{W} ρ(t), with
+/// Sokal's window: the smallest W such that W ≥ C τ_int(W). Returns
+/// (τ_int, W); a constant series counts as uncorrelated.
+pub fn integrated_time(series: &[f64]) -> (f64, usize) {
+    let rho = autocorrelation(series);
+    let mut tau = 0.5;
+
+    for (w, &r) in rho.iter().enumerate().skip(1) {
+        tau += r;
+
+        if w as f64 >= SOKAL_C * tau {
+            return (tau.max(0.5), w);
+        }
+    }
+
+    (tau.max(0.5), rho.len().saturating_sub(1))
+}
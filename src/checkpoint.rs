@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    path::Path,
+};
+
+use csv::Writer;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Where a ChaCha20 generator stands: its seed, stream and position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RngState {
+    seed: [u8; 32],
+    stream: u64,
+    word_pos: u128,
+}
+
+impl RngState {
+    pub fn of_rng(rng: &ChaCha20Rng) -> Self {
+        RngState {
+            seed: rng.get_seed(),
+            stream: rng.get_stream(),
+            word_pos: rng.get_word_pos(),
+        }
+    }
+
+    pub fn rng(&self) -> ChaCha20Rng {
+        let mut rng = ChaCha20Rng::from_seed(self.seed);
+        rng.set_stream(self.stream);
+        rng.set_word_pos(self.word_pos);
+
+        rng
+    }
+}
+
+/// Where a hysteresis loop stands between two field points.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HysteresisState {
+    /// the field in steps of h_step
+    pub h_index: i64,
+    pub step_direction: i64,
+    pub saw_max: bool,
+    /// time at the end of the previous point
+    pub prev_time: u128,
+    /// points done so far
+    pub points: usize,
+}
+
+/// Everything a hysteresis run needs to carry on from the end of a field
+/// point as if it had never stopped.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// what the run was started with, for the caller to rebuild it from
+    pub run: Value,
+    pub spins: Vec<i8>,
+    pub time: u128,
+    pub n: u128,
+    pub temp: f64,
+    pub h: f64,
+    pub state: HysteresisState,
+    pub rng: RngState,
+    /// length in bytes of every file written so far
+    pub offsets: BTreeMap<String, u64>,
+}
+
+impl Checkpoint {
+    /// Writes `checkpoint.json` in `dir`, through a temporary file so that a
+    /// crash halfway leaves the previous one intact.
+    pub fn save(&self, dir: &str) -> Result<(), Box<dyn Error>> {
+        let path = Path::new(dir).join(CHECKPOINT_FILE);
+        let tmp = path.with_extension("json.tmp");
+
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(tmp, path)?;
+
+        Ok(())
+    }
+
+    pub fn load(dir: &str) -> Result<Self, Box<dyn Error>> {
+        let path = Path::new(dir).join(CHECKPOINT_FILE);
+
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Key of `path` in the offsets of a checkpoint.
+fn key(path: &Path) -> String {
+    path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned())
+}
+
+/// Opens `path` for writing: from scratch with `header`, or, when resuming,
+/// cut back to its length at the checkpoint and appended to.
+pub fn writer(path: &Path, header: &[&str], resume: Option<&Checkpoint>) -> Result<Writer<File>, Box<dyn Error>> {
+    match resume.and_then(|c| c.offsets.get(&key(path))) {
+        Some(&len) => {
+            OpenOptions::new().write(true).open(path)?.set_len(len)?;
+
+            Ok(Writer::from_writer(OpenOptions::new().append(true).open(path)?))
+        }
+        None => {
+            let mut writer = Writer::from_path(path)?;
+            writer.write_record(header)?;
+            writer.flush()?;
+
+            Ok(writer)
+        }
+    }
+}
+
+/// Flushes `writer` of `path` and records the length of the file.
+pub fn record_offset(
+    offsets: &mut BTreeMap<String, u64>,
+    path: &Path,
+    writer: &mut Writer<File>,
+) -> Result<(), Box<dyn Error>> {
+    writer.flush()?;
+    offsets.insert(key(path), fs::metadata(path)?.len());
+
+    Ok(())
+}
@@ -4,9 +4,11 @@ use std::{
 };
 
 use clap::Parser;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Parser, Debug, Serialize)]
+use crate::{annealing::ScheduleKind, equilibrium::Criterion};
+
+#[derive(Parser, Debug, Serialize, Deserialize)]
 pub struct ArgsHysteresis {
     #[clap(short, long, default_value_t = 100)]
     pub size: usize,
@@ -20,11 +22,43 @@ pub struct ArgsHysteresis {
     #[clap(short, long, default_value_t = 2.5f64)]
     pub h_max: f64,
 
-    #[clap(short, long, default_value_t = 0.01f64)]
+    #[clap(long, default_value_t = 0.01f64)]
     pub h_step: f64,
 
     #[clap(long, multiple_values=true)]
-    pub seeds: Vec<u64>
+    pub seeds: Vec<u64>,
+
+    #[clap(long, default_value_t = 0)]
+    pub threads: usize,
+
+    #[clap(long, default_value_t = 100)]
+    pub samples: usize,
+
+    /// sweeps between samples
+    #[clap(long, default_value_t = 1)]
+    pub interval: usize,
+
+    #[clap(long, default_value_t = 0f64)]
+    pub ess: f64,
+
+    /// sweeps sampled at most at every point, whatever the ess
+    #[clap(long, default_value_t = 100000)]
+    pub max_sweeps: usize,
+
+    /// don't write every sample to series.csv, which reweighting reads
+    #[clap(long)]
+    pub no_series: bool,
+
+    #[clap(long, default_value = "window")]
+    pub criterion: Criterion,
+
+    /// relative difference of the means a criterion tolerates beyond noise
+    #[clap(long)]
+    pub eq_threshold: Option<f64>,
+
+    /// field points between checkpoints, 0 for none
+    #[clap(long, default_value_t = 10)]
+    pub checkpoint_every: usize,
 }
 
 #[derive(Parser, Debug, Serialize)]
@@ -44,10 +78,283 @@ pub struct ArgsPhase {
     #[clap(long, default_value_t = 0.01f64)]
     pub t_step: f64,
 
+    /// temperature steps past t_max taken at most while M stays positive
+    #[clap(long, default_value_t = 0)]
+    pub past_max: usize,
+
+    #[clap(long, multiple_values=true)]
+    pub seeds: Vec<u64>,
+
+    #[clap(long, default_value_t = 0)]
+    pub threads: usize,
+
+    #[clap(long, default_value_t = 100)]
+    pub samples: usize,
+
+    /// sweeps between samples
+    #[clap(long, default_value_t = 1)]
+    pub interval: usize,
+
+    #[clap(long, default_value_t = 0f64)]
+    pub ess: f64,
+
+    /// sweeps sampled at most at every point, whatever the ess
+    #[clap(long, default_value_t = 100000)]
+    pub max_sweeps: usize,
+
+    /// don't write every sample to series.csv, which reweighting reads
+    #[clap(long)]
+    pub no_series: bool,
+
+    /// don't measure G(r), leaving xi and xi_exp NaN and no correlation.csv
+    #[clap(long)]
+    pub no_correlation: bool,
+
+    #[clap(long, default_value = "window")]
+    pub criterion: Criterion,
+
+    /// relative difference of the means a criterion tolerates beyond noise
+    #[clap(long)]
+    pub eq_threshold: Option<f64>,
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsTempering {
+    #[clap(long, default_value_t = 100)]
+    pub size: usize,
+
+    #[clap(long, default_value_t = 0.5f64)]
+    pub t_min: f64,
+
+    #[clap(long, default_value_t = 4f64)]
+    pub t_max: f64,
+
+    #[clap(long, default_value_t = 16)]
+    pub replicas: usize,
+
+    #[clap(long, default_value_t = 1000)]
+    pub eq_sweeps: usize,
+
+    #[clap(long, default_value_t = 10000)]
+    pub sweeps: usize,
+
+    #[clap(long, default_value_t = 1)]
+    pub swap_interval: usize,
+
+    #[clap(long, multiple_values=true)]
+    pub seeds: Vec<u64>
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsWangLandau {
+    #[clap(long, default_value_t = 16)]
+    pub size: usize,
+
+    #[clap(long, default_value_t = 1e-6f64)]
+    pub ln_f_final: f64,
+
+    #[clap(long, default_value_t = 0.8f64)]
+    pub flatness: f64,
+
+    #[clap(long, default_value_t = 100)]
+    pub check_sweeps: usize,
+
+    #[clap(long, default_value_t = 0.1f64)]
+    pub t_min: f64,
+
+    #[clap(long, default_value_t = 5f64)]
+    pub t_max: f64,
+
+    #[clap(long, default_value_t = 0.01f64)]
+    pub t_step: f64,
+
     #[clap(long, multiple_values=true)]
     pub seeds: Vec<u64>
 }
 
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsDos {
+    #[clap(multiple_values=true)]
+    pub paths: Vec<String>,
+
+    #[clap(long, default_value_t = 0.1f64)]
+    pub t_min: f64,
+
+    #[clap(long, default_value_t = 5f64)]
+    pub t_max: f64,
+
+    #[clap(long, default_value_t = 0.01f64)]
+    pub t_step: f64,
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsAnnealing {
+    #[clap(long, default_value_t = 100)]
+    pub size: usize,
+
+    #[clap(long, default_value = "linear")]
+    pub schedule: ScheduleKind,
+
+    #[clap(long)]
+    pub table: Option<String>,
+
+    #[clap(long, default_value_t = 5f64)]
+    pub t_start: f64,
+
+    #[clap(long, default_value_t = 0.1f64)]
+    pub t_end: f64,
+
+    #[clap(long, default_value_t = 50)]
+    pub stages: usize,
+
+    #[clap(long, default_value_t = 100)]
+    pub sweeps: usize,
+
+    #[clap(long, default_value_t = 0f64)]
+    pub h: f64,
+
+    #[clap(long, multiple_values=true)]
+    pub seeds: Vec<u64>
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsExact {
+    #[clap(long, default_value_t = 4)]
+    pub size: usize,
+
+    #[clap(long, default_value_t = 0.5f64)]
+    pub t_min: f64,
+
+    #[clap(long, default_value_t = 5f64)]
+    pub t_max: f64,
+
+    #[clap(long, default_value_t = 0.05f64)]
+    pub t_step: f64,
+
+    #[clap(long, default_value_t = 0f64)]
+    pub h_min: f64,
+
+    #[clap(long, default_value_t = 0f64)]
+    pub h_max: f64,
+
+    #[clap(long, default_value_t = 0.1f64)]
+    pub h_step: f64,
+
+    #[clap(long, multiple_values=true)]
+    pub seeds: Vec<u64>
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsDemon {
+    #[clap(long, default_value_t = 100)]
+    pub size: usize,
+
+    #[clap(long, default_value_t = 0.02f64)]
+    pub e_step: f64,
+
+    #[clap(long, default_value_t = -0.2f64)]
+    pub e_max: f64,
+
+    #[clap(long, default_value_t = 100)]
+    pub eq_sweeps: usize,
+
+    #[clap(long, default_value_t = 1000)]
+    pub sweeps: usize,
+
+    #[clap(long)]
+    pub per_site: bool,
+
+    #[clap(long, multiple_values=true)]
+    pub seeds: Vec<u64>
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsCompare {
+    #[clap(multiple_values=true)]
+    pub paths: Vec<String>,
+
+    #[clap(long)]
+    pub strip: Option<usize>,
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsFit {
+    #[clap(multiple_values=true)]
+    pub paths: Vec<String>,
+
+    /// path of the aggregated output, without extension
+    #[clap(long, default_value = "data/fit")]
+    pub out: String,
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsScaling {
+    #[clap(multiple_values=true)]
+    pub paths: Vec<String>,
+
+    /// largest |T - Tc| / Tc taken into the collapse
+    #[clap(long, default_value_t = 0.1f64)]
+    pub window: f64,
+
+    /// path of the output, without extension
+    #[clap(long, default_value = "data/fss")]
+    pub out: String,
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsReweight {
+    #[clap(multiple_values=true)]
+    pub paths: Vec<String>,
+
+    /// T range reweighted to; the simulated temperatures when left out
+    #[clap(long)]
+    pub t_min: Option<f64>,
+
+    #[clap(long)]
+    pub t_max: Option<f64>,
+
+    #[clap(long, default_value_t = 0.001f64)]
+    pub t_step: f64,
+
+    /// H range reweighted to; the simulated fields when left out
+    #[clap(long)]
+    pub h_min: Option<f64>,
+
+    #[clap(long)]
+    pub h_max: Option<f64>,
+
+    #[clap(long, default_value_t = 0.01f64)]
+    pub h_step: f64,
+
+    /// combine every run by multiple histogram reweighting, instead of
+    /// reweighting the closest one
+    #[clap(long)]
+    pub wham: bool,
+
+    /// path of the output, without extension
+    #[clap(long, default_value = "data/reweight")]
+    pub out: String,
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsAggregate {
+    #[clap(multiple_values=true)]
+    pub paths: Vec<String>,
+
+    #[clap(long, default_value_t = 1000)]
+    pub resamples: usize,
+
+    /// path of the output, without extension
+    #[clap(long, default_value = "data/aggregate")]
+    pub out: String,
+}
+
+#[derive(Parser, Debug, Serialize)]
+pub struct ArgsResume {
+    /// directory of a hysteresis run holding a checkpoint.json
+    pub dir: String,
+}
+
 #[derive(Debug)]
 pub struct ArgError {}
 
@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::{
+    network::{Network, NetworkType},
+    structure::StructureFactor,
+};
+
+/// Number of sites G(r) is measured from off the regular lattice.
+const SOURCES: usize = 32;
+
+/// Sites kept of every shell around a source; larger shells are thinned out
+/// evenly to this many.
+const SHELL_SITES: usize = 256;
+
+/// Where the pairs G(r) is summed over come from.
+#[derive(Debug, Clone)]
+enum Pairs {
+    /// Regular lattice: S(k) of every wave vector, kx and ky swapped as the
+    /// structure factor leaves them, summed over the samples; its inverse
+    /// transform is ⟨s_i s_{i+r}⟩ over every pair of sites.
+    Spectrum { size: usize, power: Vec<f64> },
+    /// Any other graph: a few source sites, and of each the sites in every
+    /// shell of graph distance around it, thinned out to SHELL_SITES.
+    Shells {
+        sources: Vec<usize>,
+        shells: Vec<Vec<Vec<u32>>>,
+        /// sites kept in every bin, over all the sources
+        kept: Vec<f64>,
+        sums: Vec<f64>,
+    },
+}
+
+/// Spin–spin correlation G(r) = ⟨s_i s_j⟩ - ⟨s⟩², binned by the Euclidean
+/// (minimal image) distance on the regular lattice and by the graph
+/// distance on anything else.
+#[derive(Debug, Clone)]
+pub struct Correlation {
+    pairs_of: Pairs,
+    /// mean distance of the pairs in every bin
+    pub radii: Vec<f64>,
+    /// sites in every bin around a site
+    pub pairs: Vec<f64>,
+    samples: f64,
+    m: f64,
+}
+
+/// Minimal image distance of the displacement (x, y) on the size × size torus.
+fn euclidean(size: usize, (x, y): (usize, usize)) -> f64 {
+    let (dx, dy) = (x.min(size - x), y.min(size - y));
+
+    ((dx * dx + dy * dy) as f64).sqrt()
+}
+
+fn graph_distances(network: &Network, source: usize) -> Vec<u32> {
+    let mut dist = vec![u32::MAX; network.size * network.size];
+    let mut queue = VecDeque::from([source]);
+    dist[source] = 0;
+
+    while let Some(i) = queue.pop_front() {
+        for &j in network.lattice[i].iter() {
+            if dist[j] == u32::MAX {
+                dist[j] = dist[i] + 1;
+                queue.push_back(j);
+            }
+        }
+    }
+
+    dist
+}
+
+impl Correlation {
+    pub fn new(network: &Network, network_type: NetworkType) -> Self {
+        match network_type {
+            NetworkType::Regular => Correlation::of_spectrum(network.size),
+            NetworkType::Irregular => Correlation::of_shells(network),
+        }
+    }
+
+    fn of_spectrum(size: usize) -> Self {
+        let mut radii: Vec<f64> = vec![];
+        let mut pairs: Vec<f64> = vec![];
+
+        for i in 0..size * size {
+            let d = euclidean(size, (i % size, i / size));
+            let bin = d.round() as usize;
+
+            if bin >= pairs.len() {
+                radii.resize(bin + 1, 0.);
+                pairs.resize(bin + 1, 0.);
+            }
+            radii[bin] += d;
+            pairs[bin] += 1.;
+        }
+
+        for (r, &p) in radii.iter_mut().zip(pairs.iter()) {
+            *r /= p.max(1.);
+        }
+
+        Correlation {
+            pairs_of: Pairs::Spectrum {
+                size,
+                power: vec![0.; size * size],
+            },
+            radii,
+            pairs,
+            samples: 0.,
+            m: 0.,
+        }
+    }
+
+    fn of_shells(network: &Network) -> Self {
+        let n = network.size * network.size;
+        let sources: Vec<usize> = (0..SOURCES.min(n)).map(|k| k * n / SOURCES.min(n)).collect();
+
+        let mut pairs: Vec<f64> = vec![];
+        let shells: Vec<Vec<Vec<u32>>> = sources
+            .iter()
+            .map(|&i| {
+                let mut shells: Vec<Vec<u32>> = vec![];
+
+                for (j, d) in graph_distances(network, i).into_iter().enumerate() {
+                    if d == u32::MAX {
+                        continue;
+                    }
+                    if d as usize >= shells.len() {
+                        shells.resize(d as usize + 1, vec![]);
+                    }
+                    shells[d as usize].push(j as u32);
+                }
+
+                if shells.len() > pairs.len() {
+                    pairs.resize(shells.len(), 0.);
+                }
+                for (p, shell) in pairs.iter_mut().zip(shells.iter_mut()) {
+                    *p += shell.len() as f64 / sources.len() as f64;
+
+                    let every = shell.len().div_ceil(SHELL_SITES).max(1);
+                    *shell = shell.iter().step_by(every).copied().collect();
+                }
+
+                shells
+            })
+            .collect();
+
+        let mut kept = vec![0.; pairs.len()];
+        for source in shells.iter() {
+            for (k, shell) in kept.iter_mut().zip(source.iter()) {
+                *k += shell.len() as f64;
+            }
+        }
+
+        Correlation {
+            pairs_of: Pairs::Shells {
+                sources,
+                shells,
+                sums: vec![0.; kept.len()],
+                kept,
+            },
+            radii: (0..pairs.len()).map(|d| d as f64).collect(),
+            pairs,
+            samples: 0.,
+            m: 0.,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        match &mut self.pairs_of {
+            Pairs::Spectrum { power, .. } => power.iter_mut().for_each(|x| *x = 0.),
+            Pairs::Shells { sums, .. } => sums.iter_mut().for_each(|x| *x = 0.),
+        }
+        self.samples = 0.;
+        self.m = 0.;
+    }
+
+    /// Adds the current configuration; on the regular lattice its transform
+    /// is taken from `structure`, which must have been pushed it first.
+    pub fn push(&mut self, network: &Network, structure: Option<&StructureFactor>) {
+        match &mut self.pairs_of {
+            Pairs::Spectrum { power, .. } => {
+                if let Some(sf) = structure {
+                    power.iter_mut().zip(sf.power()).for_each(|(p, s)| *p += s);
+                }
+            }
+            Pairs::Shells { sources, shells, sums, .. } => {
+                for (&i, source) in sources.iter().zip(shells.iter()) {
+                    let s_i = network.spins[i] as f64;
+
+                    for (sum, shell) in sums.iter_mut().zip(source.iter()) {
+                        *sum += s_i * shell.iter().map(|&j| network.spins[j as usize] as f64).sum::<f64>();
+                    }
+                }
+            }
+        }
+
+        self.samples += 1.;
+        self.m += network.spins.iter().map(|&s| s as f64).sum::<f64>() / network.size2;
+    }
+
+    /// ⟨s_i s_j⟩ over the sampled configurations, for every bin.
+    fn products(&self) -> Vec<f64> {
+        let samples = self.samples.max(1.);
+
+        match &self.pairs_of {
+            Pairs::Spectrum { size, power } => {
+                let l = *size;
+                let fft = FftPlanner::new().plan_fft_inverse(l);
+
+                // rows then the transpose, as S(k) was taken, which puts
+                // kx and ky back in their places
+                let mut rows: Vec<Complex<f64>> = power.iter().map(|&p| Complex::new(p, 0.)).collect();
+                fft.process(&mut rows);
+                let mut columns: Vec<Complex<f64>> = (0..l * l).map(|i| rows[(i / l) + (i % l) * l]).collect();
+                fft.process(&mut columns);
+
+                let n = (l * l) as f64;
+                let mut sums = vec![0.; self.pairs.len()];
+                for (i, c) in columns.iter().enumerate() {
+                    sums[euclidean(l, (i % l, i / l)).round() as usize] += c.re / n;
+                }
+
+                sums.iter()
+                    .zip(self.pairs.iter())
+                    .map(|(&s, &p)| s / (p.max(1.) * samples))
+                    .collect()
+            }
+            Pairs::Shells { kept, sums, .. } => sums
+                .iter()
+                .zip(kept.iter())
+                .map(|(&s, &k)| s / (k.max(1.) * samples))
+                .collect(),
+        }
+    }
+
+    /// G(r) for every bin.
+    pub fn function(&self) -> Vec<f64> {
+        let m = self.m / self.samples.max(1.);
+
+        self.products().into_iter().map(|p| p - m * m).collect()
+    }
+
+    /// (r, G(r)) from r = 0 up to the first bin where G is no longer
+    /// positive; past that it's noise, which the r² weights would amplify.
+    fn positive(&self) -> Vec<(f64, f64, f64)> {
+        self.radii
+            .iter()
+            .zip(self.pairs.iter())
+            .zip(self.function())
+            .take_while(|(_, g)| *g > 0.)
+            .map(|((&r, &p), g)| (r, p, g))
+            .collect()
+    }
+
+    /// Second moment length, ξ² = Σ r² G(r) / (2d Σ G(r)) over the pairs
+    /// in the positive range, with d = 2 for the networks here.
+    pub fn second_moment_length(&self) -> f64 {
+        let (num, den) = self
+            .positive()
+            .iter()
+            .fold((0., 0.), |(num, den), &(r, p, g)| (num + p * r * r * g, den + p * g));
+
+        (num / (4. * den)).sqrt()
+    }
+
+    /// ξ from a least squares fit of ln G(r) = a - r / ξ, over r ≥ 1 in the
+    /// positive range.
+    pub fn exponential_length(&self) -> f64 {
+        let points: Vec<(f64, f64)> = self
+            .positive()
+            .iter()
+            .skip(1)
+            .map(|&(r, _, g)| (r, g.ln()))
+            .collect();
+
+        if points.len() < 2 {
+            return f64::NAN;
+        }
+
+        let n = points.len() as f64;
+        let x = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let y = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let sxy: f64 = points.iter().map(|p| (p.0 - x) * (p.1 - y)).sum();
+        let sxx: f64 = points.iter().map(|p| (p.0 - x).powi(2)).sum();
+
+        -sxx / sxy
+    }
+}
@@ -0,0 +1,212 @@
+use std::{error::Error, path::Path};
+
+use csv::Writer;
+use rand::{seq::SliceRandom, Rng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    child::{send, ChildMsg},
+    matrix::pos_of_index,
+    simulation::Simulation,
+};
+
+#[derive(Debug)]
+pub struct DemonConfig {
+    /// energy per spin added to the system before each step
+    pub e_step: f64,
+    /// total energy per spin at which the run stops
+    pub e_max: f64,
+    pub eq_sweeps: usize,
+    pub sweeps: usize,
+}
+
+/// Creutz demon engine driving a `Simulation` at H = 0. Every flip trades
+/// energy with a demon (one shared, or one per site) whose energy can't go
+/// negative, so system + demons keep their total fixed. All energy changes
+/// are then multiples of 2J, and demon energies are kept as integers in
+/// those units. Site demons shift by one site every sweep; a demon that
+/// only ever trades with its own spin gets stuck flipping it back and forth.
+#[derive(Debug)]
+pub struct Demon {
+    pub demons: Vec<i64>,
+    shift: usize,
+    /// visits of each demon level, since the last reset
+    pub counts: Vec<u64>,
+}
+
+impl Demon {
+    pub fn new(s: &Simulation, per_site: bool) -> Self {
+        let n = if per_site { s.network.size * s.network.size } else { 1 };
+
+        Demon {
+            demons: vec![0; n],
+            shift: 0,
+            counts: vec![],
+        }
+    }
+
+    fn quantum(s: &Simulation) -> f64 {
+        2. * s.config.j
+    }
+
+    pub fn energy(&self, s: &Simulation) -> f64 {
+        self.demons.iter().sum::<i64>() as f64 * Demon::quantum(s)
+    }
+
+    /// Raises the system energy by at least `units` quanta, flipping random
+    /// spins that cost energy; the demons then pull it back to equilibrium.
+    /// Returns the quanta added, 0 once no flip costs energy any more.
+    fn heat(&mut self, s: &mut Simulation, units: i64, rng: &mut ChaCha20Rng) -> i64 {
+        let n = s.network.size.pow(2);
+        let mut added = 0;
+
+        for _ in 0..100 * n {
+            if added >= units {
+                break;
+            }
+
+            let p = pos_of_index(s.network.size, rng.gen_range(0..n));
+            let cost = s.network.get_spin(p) as i64 * s.network.local_field(p);
+
+            if cost > 0 {
+                added += cost;
+                s.flip(p);
+            }
+        }
+
+        added
+    }
+
+    fn evolve_spin(&mut self, s: &mut Simulation, i: usize) {
+        let p = pos_of_index(s.network.size, i);
+        let d = (i + self.shift) % self.demons.len();
+        let units = s.network.get_spin(p) as i64 * s.network.local_field(p);
+
+        if units <= self.demons[d] {
+            self.demons[d] -= units;
+            s.flip(p);
+        }
+
+        let level = self.demons[d] as usize;
+        if level >= self.counts.len() {
+            self.counts.resize(level + 1, 0);
+        }
+        self.counts[level] += 1;
+    }
+
+    pub fn mc_iter(&mut self, s: &mut Simulation, rng: &mut ChaCha20Rng) {
+        let mut indices = (0..s.network.size.pow(2)).collect::<Vec<usize>>();
+        indices.shuffle(rng);
+
+        for i in indices {
+            self.evolve_spin(s, i);
+        }
+
+        self.shift = (self.shift + 1) % self.demons.len();
+    }
+
+    /// Temperature from P(E_d) ∝ exp(-E_d / kT), by a count weighted least
+    /// squares fit of ln P against E_d; 0 if only one level was visited.
+    pub fn temperature(&self, s: &Simulation) -> f64 {
+        let points: Vec<(f64, f64, f64)> = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c > 0)
+            .map(|(k, &c)| (k as f64 * Demon::quantum(s), (c as f64).ln(), c as f64))
+            .collect();
+
+        if points.len() < 2 {
+            return 0.;
+        }
+
+        let w: f64 = points.iter().map(|p| p.2).sum();
+        let x = points.iter().map(|p| p.2 * p.0).sum::<f64>() / w;
+        let y = points.iter().map(|p| p.2 * p.1).sum::<f64>() / w;
+        let sxy: f64 = points.iter().map(|p| p.2 * (p.0 - x) * (p.1 - y)).sum();
+        let sxx: f64 = points.iter().map(|p| p.2 * (p.0 - x).powi(2)).sum();
+
+        -sxx / (s.config.kb * sxy)
+    }
+
+    /// Starts from the ordered state and heats the system by `e_step` per
+    /// spin at a time, writing one row per energy like
+    /// `simulate_phase`, with T read from the demons and aE the system
+    /// energy averaged over the measurement sweeps.
+    pub fn simulate_phase(
+        &mut self,
+        s: &mut Simulation,
+        data_dist_path: &Path,
+        config: DemonConfig,
+        rand: &mut ChaCha20Rng,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut data_writer = Writer::from_path(data_dist_path)?;
+        // Write header
+        data_writer.write_record(["t", "n", "T", "M", "E", "aE"])?;
+        data_writer.flush()?;
+
+        for x in 0..s.network.size {
+            for y in 0..s.network.size {
+                s.network.spins[(x, y)] = 1;
+            }
+        }
+
+        s.set_field(0.);
+        s.refresh_measurements();
+
+        let units = (config.e_step * s.network.size2 / Demon::quantum(s)).round() as i64;
+
+        while (s.ham() + self.energy(s)) / s.network.size2 < config.e_max {
+            if self.heat(s, units.max(1), rand) == 0 {
+                send!(
+                    s.tx,
+                    s.name,
+                    format!(
+                        "no flip raises the energy past E: {}, below e_max: {}",
+                        (s.ham() + self.energy(s)) / s.network.size2,
+                        config.e_max
+                    )
+                );
+                break;
+            }
+
+            for _ in 0..config.eq_sweeps {
+                self.mc_iter(s, rand);
+                s.time += 1;
+            }
+
+            self.counts.clear();
+            let (mut m, mut e) = (0., 0.);
+
+            for _ in 0..config.sweeps {
+                self.mc_iter(s, rand);
+                s.time += 1;
+
+                m += s.mag();
+                e += s.ham();
+            }
+
+            let samples = config.sweeps.max(1) as f64;
+            let temp = self.temperature(s);
+            s.n = (config.eq_sweeps + config.sweeps) as u128;
+
+            send!(
+                s.tx,
+                s.name,
+                format!(
+                    "T: {}, M: {}, E: {}, E_demon: {}, t: {}",
+                    temp,
+                    m / samples,
+                    s.ham(),
+                    self.energy(s),
+                    s.time
+                )
+            );
+
+            data_writer.serialize((s.time, s.n, temp, m / samples, s.ham(), e / samples))?;
+            data_writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,25 +1,136 @@
-use std::{error::Error, fs::File, io::Write, path::Path};
+use std::{
+    error::Error,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use crate::{
-    cli::{ArgsHysteresis, ArgsPhase},
+    cli::{
+        ArgsAnnealing, ArgsDemon, ArgsExact, ArgsHysteresis, ArgsPhase, ArgsTempering,
+        ArgsWangLandau,
+    },
+    loops::LoopMetrics,
     matrix::Matrix,
+    network::NetworkType,
 };
 
 use serde::Serialize;
+use serde_json::Value;
+
+/// Name of the network a run was on, read back from its descriptor; runs
+/// that predate `network_type` go by their path.
+pub fn network_of(desc: &Value, desc_path: &Path) -> String {
+    match serde_json::from_value::<NetworkType>(desc["network_type"].clone()) {
+        Ok(network_type) => network_type.to_string(),
+        Err(_) if desc_path.to_string_lossy().contains("/irregular/") => String::from("irregular"),
+        Err(_) => String::from("regular"),
+    }
+}
+
+/// The data CSV of a run: the one beside its descriptor, else the path it
+/// records, which is relative to wherever the run was started from.
+pub fn data_path_of(desc: &Value, desc_path: &Path) -> PathBuf {
+    let beside = desc_path.with_file_name("data.csv");
+
+    match desc["data_path"].as_str() {
+        Some(path) if !beside.exists() => PathBuf::from(path),
+        _ => beside,
+    }
+}
 
 #[derive(Serialize)]
 pub struct PhaseDescriptor<'a> {
     pub config: &'a ArgsPhase,
+    pub network_type: NetworkType,
     pub lattice: Matrix<Vec<usize>>,
     pub deg_mse: f64,
     pub deg_avg: f64,
     pub seed: u64,
     pub data_path: &'a Path,
+    pub theory_path: &'a Path,
+    /// definition of every column of the data CSV
+    pub columns: Vec<(&'static str, &'static str)>,
 }
 
 #[derive(Serialize)]
 pub struct HysteresisDescriptor<'a> {
     pub config: &'a ArgsHysteresis,
+    pub network_type: NetworkType,
+    pub lattice: Matrix<Vec<usize>>,
+    pub deg_mse: f64,
+    pub deg_avg: f64,
+    pub seed: u64,
+    pub temp: f64,
+    pub data_path: &'a Path,
+    pub theory_path: &'a Path,
+    pub metrics: LoopMetrics,
+    /// definition of every column of the data CSV
+    pub columns: Vec<(&'static str, &'static str)>,
+}
+
+/// Loop metrics of every seed at one temperature, with their mean and its
+/// standard error.
+#[derive(Serialize)]
+pub struct LoopsDescriptor<'a> {
+    pub config: &'a ArgsHysteresis,
+    pub temp: f64,
+    pub seeds: &'a Vec<u64>,
+    pub metrics: Vec<LoopMetrics>,
+    pub mean: LoopMetrics,
+    pub error: LoopMetrics,
+}
+
+#[derive(Serialize)]
+pub struct TemperingDescriptor<'a> {
+    pub config: &'a ArgsTempering,
+    pub lattice: Matrix<Vec<usize>>,
+    pub deg_mse: f64,
+    pub deg_avg: f64,
+    pub seed: u64,
+    pub temps: Vec<f64>,
+    pub data_path: &'a Path,
+    pub swaps_path: &'a Path,
+}
+
+#[derive(Serialize)]
+pub struct WangLandauDescriptor<'a> {
+    pub config: &'a ArgsWangLandau,
+    pub lattice: Matrix<Vec<usize>>,
+    pub deg_mse: f64,
+    pub deg_avg: f64,
+    pub seed: u64,
+    pub dos_path: &'a Path,
+    pub thermo_path: &'a Path,
+}
+
+#[derive(Serialize)]
+pub struct AnnealingDescriptor<'a> {
+    pub config: &'a ArgsAnnealing,
+    pub lattice: Matrix<Vec<usize>>,
+    pub deg_mse: f64,
+    pub deg_avg: f64,
+    pub seed: u64,
+    pub temps: &'a Vec<f64>,
+    pub best_energy: f64,
+    pub best_time: u128,
+    pub data_path: &'a Path,
+    pub best_path: &'a Path,
+}
+
+#[derive(Serialize)]
+pub struct ExactDescriptor<'a> {
+    pub config: &'a ArgsExact,
+    pub lattice: Matrix<Vec<usize>>,
+    pub deg_mse: f64,
+    pub deg_avg: f64,
+    pub seed: u64,
+    pub data_path: &'a Path,
+}
+
+#[derive(Serialize)]
+pub struct DemonDescriptor<'a> {
+    pub config: &'a ArgsDemon,
     pub lattice: Matrix<Vec<usize>>,
     pub deg_mse: f64,
     pub deg_avg: f64,
@@ -41,3 +152,15 @@ pub trait Descriptor: Serialize {
 impl<'a> Descriptor for PhaseDescriptor<'a> {}
 
 impl<'a> Descriptor for HysteresisDescriptor<'a> {}
+
+impl<'a> Descriptor for LoopsDescriptor<'a> {}
+
+impl<'a> Descriptor for TemperingDescriptor<'a> {}
+
+impl<'a> Descriptor for WangLandauDescriptor<'a> {}
+
+impl<'a> Descriptor for AnnealingDescriptor<'a> {}
+
+impl<'a> Descriptor for ExactDescriptor<'a> {}
+
+impl<'a> Descriptor for DemonDescriptor<'a> {}
@@ -0,0 +1,88 @@
+use crate::network::Network;
+
+/// Like-spin domains of a configuration: the connected components of the
+/// graph left after cutting every unsatisfied bond, found by union–find
+/// (Hoshen–Kopelman on an arbitrary graph).
+#[derive(Debug)]
+pub struct Domains {
+    /// number of sites in every domain
+    pub sizes: Vec<usize>,
+    /// domain wall length, the number of unsatisfied bonds
+    pub walls: usize,
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+
+    i
+}
+
+impl Domains {
+    pub fn of_network(network: &Network) -> Self {
+        let n = network.size * network.size;
+        let mut parent: Vec<usize> = (0..n).collect();
+        let mut walls = 0;
+
+        for i in 0..n {
+            for &j in network.lattice[i].iter().filter(|&&j| j > i) {
+                if network.spins[i] != network.spins[j] {
+                    walls += 1;
+                    continue;
+                }
+
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut label_of_root = vec![usize::MAX; n];
+        let mut sizes = vec![];
+
+        for i in 0..n {
+            let root = find(&mut parent, i);
+
+            if label_of_root[root] == usize::MAX {
+                label_of_root[root] = sizes.len();
+                sizes.push(0);
+            }
+
+            sizes[label_of_root[root]] += 1;
+        }
+
+        Domains {
+            sizes,
+            walls,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    pub fn largest_fraction(&self) -> f64 {
+        let n: usize = self.sizes.iter().sum();
+
+        self.sizes.iter().max().map_or(0., |&m| m as f64 / n.max(1) as f64)
+    }
+
+    /// (domain size, number of domains of that size), by increasing size.
+    pub fn distribution(&self) -> Vec<(usize, usize)> {
+        let mut sizes = self.sizes.clone();
+        sizes.sort_unstable();
+
+        let mut dist: Vec<(usize, usize)> = vec![];
+        for size in sizes {
+            match dist.last_mut() {
+                Some((s, count)) if *s == size => *count += 1,
+                _ => dist.push((size, 1)),
+            }
+        }
+
+        dist
+    }
+}
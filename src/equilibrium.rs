@@ -0,0 +1,228 @@
+use std::{collections::VecDeque, error::Error, fmt, io, str::FromStr};
+
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors,
+    simulation::{Simulation, StateSnapshot},
+};
+
+/// Standard errors two window means may differ by, on top of `eq_threshold`.
+const WINDOW_SIGMAS: f64 = 2.;
+
+/// Geweke compares the first 10% of the last two windows with their last half.
+const GEWEKE_FIRST: f64 = 0.1;
+const GEWEKE_LAST: f64 = 0.5;
+const GEWEKE_Z: f64 = 2.;
+
+/// Sweeps a point may take before it is measured whether or not the
+/// criterion fired.
+pub const MAX_SWEEPS: u128 = 100_000_000;
+
+/// When a point counts as equilibrated. `equilibrium_steps` is the number
+/// of sweeps for `Fixed` and the window the others are checked over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Criterion {
+    /// no change at all in E and M between two sweeps
+    Exact,
+    Fixed,
+    /// means of E and |M| over the last two windows agree
+    Window,
+    /// the last window agrees with that of a replica started from random spins,
+    /// phase runs only: inside a hysteresis loop the replica need not reach
+    /// the metastable branch
+    HotCold,
+    /// the start and the end of the last two windows agree
+    Geweke,
+}
+
+impl FromStr for Criterion {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Criterion::Exact),
+            "fixed" => Ok(Criterion::Fixed),
+            "window" => Ok(Criterion::Window),
+            "hot-cold" | "hotcold" => Ok(Criterion::HotCold),
+            "geweke" => Ok(Criterion::Geweke),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown criterion {:?}, expected exact, fixed, window, hot-cold or geweke", s),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Criterion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Criterion::Exact => "exact",
+            Criterion::Fixed => "fixed",
+            Criterion::Window => "window",
+            Criterion::HotCold => "hot-cold",
+            Criterion::Geweke => "geweke",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Errs for a criterion that cannot equilibrate the points of a hysteresis loop.
+pub fn check_hysteresis(criterion: Criterion) -> Result<(), Box<dyn Error>> {
+    match criterion {
+        Criterion::HotCold => Err(format!(
+            "the {} criterion needs equilibrium, use another one for hysteresis loops",
+            criterion
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// How a point was equilibrated: by `criterion`, or by running out of
+/// sweeps when it never `fired`.
+#[derive(Debug, Clone, Copy)]
+pub struct Equilibration {
+    pub criterion: Criterion,
+    pub fired: bool,
+    pub sweeps: u128,
+}
+
+fn mean_var(xs: &[f64]) -> (f64, f64) {
+    let n = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+
+    (mean, xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.).max(1.))
+}
+
+fn windows_agree(a: &[f64], b: &[f64], threshold: f64) -> bool {
+    let ((ma, va), (mb, vb)) = (mean_var(a), mean_var(b));
+    let noise = (va / a.len() as f64 + vb / b.len() as f64).sqrt();
+
+    (ma - mb).abs() <= threshold * ma.abs().max(mb.abs()) + WINDOW_SIGMAS * noise
+}
+
+/// Appends `x` to `history`, dropping what is older than two windows.
+fn push(history: &mut VecDeque<f64>, x: f64, window: usize) {
+    if history.len() == 2 * window {
+        history.pop_front();
+    }
+
+    history.push_back(x);
+}
+
+fn geweke(series: &[f64], threshold: f64) -> bool {
+    let n = series.len();
+    let first = &series[..((n as f64 * GEWEKE_FIRST) as usize).max(2)];
+    let last = &series[n - ((n as f64 * GEWEKE_LAST) as usize).max(2)..];
+
+    let (m_first, m_last) = (mean_var(first).0, mean_var(last).0);
+    let error = errors::binning(first).hypot(errors::binning(last));
+
+    (m_first - m_last).abs() <= threshold * m_last.abs() + GEWEKE_Z * error
+}
+
+/// Sweeps `s` at its current T and H until its criterion fires or
+/// `max_sweeps` pass, advancing `time` and `n`; `each` sees every sweep
+/// along with the state before it.
+pub fn equilibrate(
+    s: &mut Simulation,
+    max_sweeps: u128,
+    rand: &mut ChaCha20Rng,
+    each: impl FnMut(&mut Simulation, &StateSnapshot) -> Result<(), Box<dyn Error>>,
+) -> Result<Equilibration, Box<dyn Error>> {
+    equilibrate_with(s, max_sweeps, rand, |s, rng| s.mc_iter(rng), each)
+}
+
+/// `equilibrate` with every sweep of `s` made by `sweep`, for other
+/// dynamics; the hot replica of `HotCold` still takes Metropolis sweeps,
+/// which sample the same ensemble.
+pub fn equilibrate_with(
+    s: &mut Simulation,
+    max_sweeps: u128,
+    rand: &mut ChaCha20Rng,
+    mut sweep: impl FnMut(&mut Simulation, &mut ChaCha20Rng),
+    mut each: impl FnMut(&mut Simulation, &StateSnapshot) -> Result<(), Box<dyn Error>>,
+) -> Result<Equilibration, Box<dyn Error>> {
+    let criterion = s.config.criterion;
+    let threshold = s.config.eq_threshold;
+    let window = s.config.equilibrium_steps.max(1);
+
+    let mut hot = match criterion {
+        Criterion::HotCold => Some(s.replica(s.config.temp, rand)),
+        _ => None,
+    };
+
+    // E per spin and |M| of the last two windows of sweeps, and the same of the hot replica
+    let history = || VecDeque::with_capacity(2 * window + 1);
+    let (mut energies, mut mags) = (history(), history());
+    let (mut hot_energies, mut hot_mags) = (history(), history());
+    let mut sweeps = 0;
+
+    while sweeps < max_sweeps {
+        let prev_state = StateSnapshot::of_simulation(s);
+
+        sweep(s, rand);
+        s.time += 1;
+        s.n += 1;
+        sweeps += 1;
+
+        each(s, &prev_state)?;
+        push(&mut energies, s.ham() / s.network.size2, window);
+        push(&mut mags, s.mag().abs(), window);
+
+        if let Some(h) = hot.as_mut() {
+            h.mc_iter(rand);
+            push(&mut hot_energies, h.ham() / h.network.size2, window);
+            push(&mut hot_mags, h.mag().abs(), window);
+        }
+
+        let at_window = sweeps % window as u128 == 0;
+        let full = energies.len() == 2 * window;
+        let last = energies.len().saturating_sub(window);
+
+        let fired = match criterion {
+            Criterion::Exact => s.is_at_equilibrium(&prev_state),
+            Criterion::Fixed => sweeps >= window as u128,
+            Criterion::Window => {
+                at_window && full && {
+                    let (energies, mags) = (energies.make_contiguous(), mags.make_contiguous());
+
+                    windows_agree(&energies[..last], &energies[last..], threshold)
+                        && windows_agree(&mags[..last], &mags[last..], threshold)
+                }
+            }
+            Criterion::HotCold => {
+                at_window
+                    && windows_agree(
+                        &energies.make_contiguous()[last..],
+                        &hot_energies.make_contiguous()[last..],
+                        threshold,
+                    )
+                    && windows_agree(&mags.make_contiguous()[last..], &hot_mags.make_contiguous()[last..], threshold)
+            }
+            Criterion::Geweke => {
+                at_window
+                    && full
+                    && geweke(energies.make_contiguous(), threshold)
+                    && geweke(mags.make_contiguous(), threshold)
+            }
+        };
+
+        if fired {
+            return Ok(Equilibration {
+                criterion,
+                fired: true,
+                sweeps,
+            });
+        }
+    }
+
+    Ok(Equilibration {
+        criterion,
+        fired: false,
+        sweeps,
+    })
+}
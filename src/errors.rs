@@ -0,0 +1,198 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+use csv::{Reader, Writer};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde_json::Value;
+
+use crate::descriptor;
+
+/// Fewest blocks a binning level is trusted with.
+const MIN_BLOCKS: usize = 32;
+
+/// Blocks the jackknife leaves out one at a time.
+pub const JACKKNIFE_BLOCKS: usize = 32;
+
+/// Columns `aggregate` bootstraps across seeds, whichever the runs have,
+/// each with the column read instead from runs that predate it: the sample
+/// means, never the last sweep, or else the averages over every sweep.
+const COLUMNS: [(&str, &str); 6] = [
+    ("sM", "aM"),
+    ("absM", "absM"),
+    ("sE", "aE"),
+    ("X", "X"),
+    ("C", "C"),
+    ("U4", "U4"),
+];
+
+fn mean_error(xs: &[f64]) -> f64 {
+    let n = xs.len() as f64;
+    let mean = xs.iter().sum::<f64>() / n;
+
+    (xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.) / n).sqrt()
+}
+
+/// Error of the mean of a correlated series by binning (Flyvbjerg–Petersen):
+/// pairs are averaged level after level, and the largest naive error over
+/// the levels with at least MIN_BLOCKS blocks is kept, the plateau it
+/// reaches once blocks outgrow the autocorrelation time.
+pub fn binning(series: &[f64]) -> f64 {
+    if series.len() < 2 {
+        return f64::NAN;
+    }
+
+    let mut blocks = series.to_vec();
+    let mut error = mean_error(&blocks);
+
+    while blocks.len() / 2 >= MIN_BLOCKS {
+        blocks = blocks.chunks_exact(2).map(|c| (c[0] + c[1]) / 2.).collect();
+        error = error.max(mean_error(&blocks));
+    }
+
+    error
+}
+
+/// Jackknife error of an estimator, given the estimates with each block
+/// left out in turn.
+pub fn jackknife(estimates: &[f64]) -> f64 {
+    let n = estimates.len() as f64;
+    let mean = estimates.iter().sum::<f64>() / n;
+
+    ((n - 1.) / n * estimates.iter().map(|x| (x - mean).powi(2)).sum::<f64>()).sqrt()
+}
+
+/// Bootstrap error of the mean of independent values, such as one per seed.
+pub fn bootstrap(values: &[f64], resamples: usize, rng: &mut ChaCha20Rng) -> f64 {
+    if values.len() < 2 {
+        return f64::NAN;
+    }
+
+    let means: Vec<f64> = (0..resamples)
+        .map(|_| (0..values.len()).map(|_| values[rng.gen_range(0..values.len())]).sum::<f64>() / values.len() as f64)
+        .collect();
+
+    let n = means.len() as f64;
+    let mean = means.iter().sum::<f64>() / n;
+
+    (means.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.)).sqrt()
+}
+
+struct Group {
+    temp: f64,
+    h: f64,
+    values: Vec<Vec<f64>>,
+}
+
+/// Rounds a T or H so that the same grid point of two runs compares equal.
+fn grid_key(x: f64) -> i64 {
+    (x * 1e9).round() as i64
+}
+
+/// Per point of a set of runs repeated over seeds, the mean of every column
+/// and its bootstrap error. Points are the T of phase runs and the H of
+/// hysteresis loops, matched by their place along the run since a loop
+/// passes every H more than once; runs grouped together whose grids differ
+/// are an error.
+pub fn aggregate(desc_paths: &[String], resamples: usize, out: &str) -> Result<String, Box<dyn Error>> {
+    let mut groups: BTreeMap<(String, u64, i64, usize), Group> = BTreeMap::new();
+    // the grid of the first run of every group, and where it came from
+    let mut grids: BTreeMap<(String, u64, i64), (Vec<i64>, String)> = BTreeMap::new();
+
+    for path in desc_paths {
+        let desc_path = Path::new(path);
+        let desc: Value = serde_json::from_str(&fs::read_to_string(desc_path)?)?;
+        let data_path = descriptor::data_path_of(&desc, desc_path);
+        let network = descriptor::network_of(&desc, desc_path);
+        let size = desc["config"]["size"].as_u64().unwrap_or(0);
+        let run_temp = desc["temp"].as_f64().unwrap_or(f64::NAN);
+
+        let mut reader = Reader::from_path(&data_path)?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h == name);
+        let (col_t, col_h) = (column("T"), column("H"));
+        let columns: Vec<Option<usize>> = COLUMNS.iter().map(|&(c, old)| column(c).or_else(|| column(old))).collect();
+
+        let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+        let run = (network.clone(), size, grid_key(run_temp));
+        let grid: Vec<i64> = records
+            .iter()
+            .map(|record| {
+                let point = col_t.or(col_h).and_then(|c| record[c].trim().parse::<f64>().ok());
+
+                grid_key(point.unwrap_or(f64::NAN))
+            })
+            .collect();
+
+        match grids.get(&run) {
+            Some((first, first_path)) if *first != grid => {
+                return Err(format!(
+                    "{} and {} are not on the same {} grid",
+                    first_path,
+                    path,
+                    if col_t.is_some() { "T" } else { "H" }
+                )
+                .into());
+            }
+            Some(_) => (),
+            None => {
+                grids.insert(run.clone(), (grid, path.to_owned()));
+            }
+        }
+
+        for (row, record) in records.iter().enumerate() {
+            let get = |c: Option<usize>| c.and_then(|c| record[c].trim().parse::<f64>().ok()).unwrap_or(f64::NAN);
+
+            let group = groups
+                .entry((run.0.clone(), run.1, run.2, row))
+                .or_insert(Group {
+                    temp: col_t.map_or(run_temp, |_| get(col_t)),
+                    h: col_h.map_or(0., |_| get(col_h)),
+                    values: vec![vec![]; COLUMNS.len()],
+                });
+
+            for (values, &c) in group.values.iter_mut().zip(columns.iter()) {
+                values.push(get(c));
+            }
+        }
+    }
+
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let out_path = format!("{}.csv", out);
+
+    if let Some(dir) = Path::new(out).parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut writer = Writer::from_path(&out_path)?;
+    let mut header = vec!["network".to_string(), "size".into(), "row".into(), "T".into(), "H".into(), "seeds".into()];
+    for (c, _) in COLUMNS {
+        header.push(c.to_string());
+        header.push(format!("{}_err", c));
+    }
+    writer.write_record(&header)?;
+
+    for ((network, size, _, row), group) in groups {
+        let mut record = vec![
+            network,
+            size.to_string(),
+            row.to_string(),
+            group.temp.to_string(),
+            group.h.to_string(),
+            group.values[0].len().to_string(),
+        ];
+
+        for values in group.values.iter() {
+            let finite: Vec<f64> = values.iter().copied().filter(|x| x.is_finite()).collect();
+            let mean = finite.iter().sum::<f64>() / finite.len() as f64;
+
+            record.push(mean.to_string());
+            record.push(bootstrap(&finite, resamples, &mut rng).to_string());
+        }
+
+        writer.write_record(&record)?;
+    }
+
+    writer.flush()?;
+
+    Ok(out_path)
+}
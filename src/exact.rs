@@ -0,0 +1,150 @@
+use std::{error::Error, path::Path};
+
+use csv::Writer;
+
+use crate::{network::Network, simulation::grid_point};
+
+/// Largest system that is still enumerated, 2^MAX_SPINS states.
+pub const MAX_SPINS: usize = 30;
+
+#[derive(Debug)]
+pub struct ExactConfig {
+    pub t_min: f64,
+    pub t_max: f64,
+    pub t_step: f64,
+    pub h_min: f64,
+    pub h_max: f64,
+    pub h_step: f64,
+    pub j: f64,
+    pub kb: f64,
+}
+
+/// Number of states with a given bond sum Σ s_i s_j and spin sum Σ s_i,
+/// which is all that the partition function at any (T, H) depends on.
+#[derive(Debug)]
+pub struct Exact {
+    pub spins: usize,
+    pub bonds: usize,
+    counts: Vec<u64>,
+}
+
+fn steps(min: f64, max: f64, step: f64) -> Vec<f64> {
+    let n = ((max - min) / step).round().max(0.) as usize;
+
+    (0..=n).map(|k| grid_point(min, k as i64, step)).collect()
+}
+
+/// Errs when a size × size lattice has too many spins to enumerate.
+pub fn check_size(size: usize) -> Result<(), Box<dyn Error>> {
+    if size * size > MAX_SPINS {
+        return Err(format!(
+            "too many spins for exact enumeration: {} (at most {}, size {})",
+            size * size,
+            MAX_SPINS,
+            (MAX_SPINS as f64).sqrt() as usize
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+impl Exact {
+    fn index(&self, bond_sum: i64, spin_sum: i64) -> usize {
+        let width = 2 * self.spins + 1;
+
+        (bond_sum + self.bonds as i64) as usize * width + (spin_sum + self.spins as i64) as usize
+    }
+
+    /// Visits all 2^N states in Gray-code order, so that consecutive states
+    /// differ by a single flip and the sums are updated incrementally.
+    pub fn enumerate(network: &Network) -> Result<Self, Box<dyn Error>> {
+        let n = network.size * network.size;
+        check_size(network.size)?;
+
+        let bonds = network.lattice.iter().map(|ns| ns.len()).sum::<usize>() / 2;
+        let mut exact = Exact {
+            spins: n,
+            bonds,
+            counts: vec![0; (2 * bonds + 1) * (2 * n + 1)],
+        };
+
+        let mut spins = vec![-1i64; n];
+        let mut bond_sum = bonds as i64;
+        let mut spin_sum = -(n as i64);
+
+        let first = exact.index(bond_sum, spin_sum);
+        exact.counts[first] += 1;
+
+        for k in 1..(1u64 << n) {
+            let i = k.trailing_zeros() as usize;
+            let field = network.lattice[i].iter().map(|&j| spins[j]).sum::<i64>();
+
+            bond_sum -= 2 * spins[i] * field;
+            spin_sum -= 2 * spins[i];
+            spins[i] *= -1;
+
+            let index = exact.index(bond_sum, spin_sum);
+            exact.counts[index] += 1;
+        }
+
+        Ok(exact)
+    }
+
+    /// Exact canonical averages at (T, H); returns `[T, H, M, absM, E, C, X]`.
+    pub fn observables(&self, temp: f64, h: f64, j: f64, kb: f64) -> Vec<f64> {
+        let beta = 1. / (kb * temp);
+        let n = self.spins as f64;
+
+        let states: Vec<(f64, f64, f64)> = (-(self.bonds as i64)..=self.bonds as i64)
+            .flat_map(|b| (-(self.spins as i64)..=self.spins as i64).map(move |m| (b, m)))
+            .filter_map(|(b, m)| {
+                let count = self.counts[self.index(b, m)];
+
+                (count > 0).then(|| {
+                    let e = -j * b as f64 - h * m as f64;
+
+                    ((count as f64).ln() - beta * e, e, m as f64 / n)
+                })
+            })
+            .collect();
+
+        let max = states.iter().fold(f64::NEG_INFINITY, |u, s| u.max(s.0));
+        let z: f64 = states.iter().map(|s| (s.0 - max).exp()).sum();
+
+        let avg = |f: &dyn Fn(f64, f64) -> f64| -> f64 {
+            states.iter().map(|s| (s.0 - max).exp() * f(s.1, s.2)).sum::<f64>() / z
+        };
+
+        let e = avg(&|e, _| e);
+        let e2 = avg(&|e, _| e * e);
+        let m = avg(&|_, m| m);
+        let m_abs = avg(&|_, m| m.abs());
+        let m2 = avg(&|_, m| m * m);
+
+        vec![
+            temp,
+            h,
+            m,
+            m_abs,
+            e,
+            beta * beta * kb * (e2 - e * e) / n,
+            beta * n * (m2 - m_abs * m_abs),
+        ]
+    }
+
+    pub fn save(&self, path: &Path, config: &ExactConfig) -> Result<(), Box<dyn Error>> {
+        let mut writer = Writer::from_path(path)?;
+        writer.write_record(["T", "H", "M", "absM", "E", "C", "X"])?;
+
+        for &h in steps(config.h_min, config.h_max, config.h_step).iter() {
+            for &temp in steps(config.t_min, config.t_max, config.t_step).iter() {
+                writer.serialize(self.observables(temp, h, config.j, config.kb))?;
+            }
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
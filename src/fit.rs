@@ -0,0 +1,321 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use csv::{Reader, Writer};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::descriptor;
+
+/// Bounds every parameter is kept within, as in `cmp/fit_phase.py`.
+const LOWER: f64 = 1e-12;
+const UPPER: f64 = 5.;
+
+const MAX_ITERATIONS: usize = 1000;
+const TOLERANCE: f64 = 1e-12;
+
+/// M(T) = M0 sign(v) |v|^β with v = 1 - T / Tc.
+pub fn power_law(p: &[f64; 3], t: f64) -> f64 {
+    let v = 1. - t / p[1];
+
+    p[0] * v.signum() * v.abs().powf(p[2])
+}
+
+/// Derivatives of `power_law` in M0, Tc and β.
+fn gradient(p: &[f64; 3], t: f64) -> [f64; 3] {
+    let v = 1. - t / p[1];
+    let a = v.abs();
+
+    if a == 0. {
+        return [0.; 3];
+    }
+
+    let base = v.signum() * a.powf(p[2]);
+
+    [base, p[0] * p[2] * a.powf(p[2] - 1.) * t / (p[1] * p[1]), p[0] * base * a.ln()]
+}
+
+fn invert(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+
+    if det.abs() < f64::MIN_POSITIVE {
+        return None;
+    }
+
+    let c = |i: usize, j: usize| {
+        let (r0, r1) = ((i + 1) % 3, (i + 2) % 3);
+        let (c0, c1) = ((j + 1) % 3, (j + 2) % 3);
+
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+
+    // the adjugate is the transposed cofactor matrix
+    Some(std::array::from_fn(|i| std::array::from_fn(|j| c(j, i) / det)))
+}
+
+/// Port of `slice_data`: the leading run of M ≥ 0 up to the first M ≤ 0,
+/// less the saturated points (M > 0.96) at the start.
+pub fn slice_data(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut valid = vec![];
+
+    for &(t, m) in points {
+        if m >= 0. {
+            valid.push((t, m));
+        }
+        if m <= 0. {
+            break;
+        }
+    }
+
+    let left = valid.iter().filter(|p| p.1 > 0.96).count();
+
+    valid.split_off(left.min(valid.len()))
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PowerLawFit {
+    pub points: usize,
+    pub m0: f64,
+    pub m0_err: f64,
+    pub tc: f64,
+    pub tc_err: f64,
+    pub beta: f64,
+    pub beta_err: f64,
+    pub chi2: f64,
+}
+
+impl PowerLawFit {
+    /// Levenberg–Marquardt least squares within [LOWER, UPPER], with the
+    /// errors from the covariance χ² / (n - 3) (JᵀJ)⁻¹, like `curve_fit`.
+    pub fn of_points(points: &[(f64, f64)]) -> Option<Self> {
+        if points.len() < 3 {
+            return None;
+        }
+
+        let chi2 = |p: &[f64; 3]| points.iter().map(|&(t, m)| (m - power_law(p, t)).powi(2)).sum::<f64>();
+        let normal = |p: &[f64; 3]| {
+            let mut jtj = [[0.; 3]; 3];
+            let mut jtr = [0.; 3];
+
+            for &(t, m) in points {
+                let g = gradient(p, t);
+                let r = m - power_law(p, t);
+
+                for (i, gi) in g.iter().enumerate() {
+                    jtr[i] += gi * r;
+                    for (j, gj) in g.iter().enumerate() {
+                        jtj[i][j] += gi * gj;
+                    }
+                }
+            }
+
+            (jtj, jtr)
+        };
+
+        // Tc just past the last point, so every v starts positive
+        let t_last = points.iter().fold(0f64, |u, p| u.max(p.0));
+        let m_first = points.iter().fold(0f64, |u, p| u.max(p.1));
+        let mut p = [m_first.clamp(LOWER, UPPER), (1.05 * t_last).clamp(LOWER, UPPER), 0.125];
+        let mut cost = chi2(&p);
+        let mut lambda = 1e-3;
+
+        for _ in 0..MAX_ITERATIONS {
+            let (jtj, jtr) = normal(&p);
+            let mut damped = jtj;
+            for (i, row) in damped.iter_mut().enumerate() {
+                row[i] += lambda * jtj[i][i].max(TOLERANCE);
+            }
+
+            let inv = match invert(&damped) {
+                Some(inv) => inv,
+                None => break,
+            };
+
+            let next: [f64; 3] = std::array::from_fn(|i| {
+                let step: f64 = (0..3).map(|j| inv[i][j] * jtr[j]).sum();
+
+                (p[i] + step).clamp(LOWER, UPPER)
+            });
+            let next_cost = chi2(&next);
+
+            if next_cost.is_finite() && next_cost <= cost {
+                let converged = cost - next_cost <= TOLERANCE * cost.max(TOLERANCE);
+
+                p = next;
+                cost = next_cost;
+                lambda /= 10.;
+
+                if converged {
+                    break;
+                }
+            } else {
+                lambda *= 10.;
+
+                if lambda > 1e12 {
+                    break;
+                }
+            }
+        }
+
+        let dof = points.len() as f64 - 3.;
+        let errors = match invert(&normal(&p).0) {
+            Some(cov) if dof > 0. => std::array::from_fn(|i| (cov[i][i] * cost / dof).abs().sqrt()),
+            _ => [f64::NAN; 3],
+        };
+
+        Some(PowerLawFit {
+            points: points.len(),
+            m0: p[0],
+            m0_err: errors[0],
+            tc: p[1],
+            tc_err: errors[1],
+            beta: p[2],
+            beta_err: errors[2],
+            chi2: cost,
+        })
+    }
+}
+
+/// Fit of one phase run, identified the way `cmp/cmp_phase.py` groups them.
+#[derive(Debug, Serialize)]
+pub struct SeedFit {
+    pub desc_path: PathBuf,
+    pub network: String,
+    pub size: u64,
+    pub seed: u64,
+    pub fit: PowerLawFit,
+}
+
+/// Mean over the seeds of one network and size, with the standard error.
+#[derive(Debug, Serialize)]
+pub struct GroupFit {
+    pub network: String,
+    pub size: u64,
+    pub seeds: usize,
+    pub m0: f64,
+    pub m0_err: f64,
+    pub tc: f64,
+    pub tc_err: f64,
+    pub beta: f64,
+    pub beta_err: f64,
+}
+
+/// Fits the sliced ⟨|M|⟩(T) of a phase run from its `desc.json`, falling back
+/// on M for runs that predate the column, and writes the result into
+/// `fit.json` beside the descriptor.
+pub fn fit_phase(desc_path: &Path) -> Result<SeedFit, Box<dyn Error>> {
+    let desc: Value = serde_json::from_str(&fs::read_to_string(desc_path)?)?;
+    let data_path = descriptor::data_path_of(&desc, desc_path);
+
+    let mut reader = Reader::from_path(&data_path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name).ok_or(format!("no {} column", name));
+    let (col_t, col_m) = (column("T")?, column("absM").or_else(|_| column("M"))?);
+
+    let mut points = vec![];
+    for record in reader.records() {
+        let record = record?;
+        points.push((record[col_t].trim().parse::<f64>()?, record[col_m].trim().parse::<f64>()?));
+    }
+
+    let sliced = slice_data(&points);
+    let fit = PowerLawFit::of_points(&sliced)
+        .ok_or(format!("{}: too few points to fit ({})", desc_path.display(), sliced.len()))?;
+
+    let network = descriptor::network_of(&desc, desc_path);
+
+    let result = SeedFit {
+        desc_path: desc_path.to_path_buf(),
+        network,
+        size: desc["config"]["size"].as_u64().unwrap_or(0),
+        seed: desc["seed"].as_u64().unwrap_or(0),
+        fit,
+    };
+
+    fs::write(desc_path.with_file_name("fit.json"), serde_json::to_string(&result)?)?;
+
+    Ok(result)
+}
+
+/// Groups the per seed fits by network and size.
+pub fn aggregate(fits: &[SeedFit]) -> Vec<GroupFit> {
+    let mut keys: Vec<(String, u64)> = fits.iter().map(|f| (f.network.clone(), f.size)).collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .map(|(network, size)| {
+            let group: Vec<&PowerLawFit> = fits
+                .iter()
+                .filter(|f| f.network == network && f.size == size)
+                .map(|f| &f.fit)
+                .collect();
+
+            let n = group.len() as f64;
+            let stats = |x: fn(&PowerLawFit) -> f64| {
+                let mean = group.iter().map(|f| x(f)).sum::<f64>() / n;
+                let var = group.iter().map(|f| (x(f) - mean).powi(2)).sum::<f64>() / (n - 1.);
+
+                (mean, (var / n).sqrt())
+            };
+
+            let (m0, m0_err) = stats(|f| f.m0);
+            let (tc, tc_err) = stats(|f| f.tc);
+            let (beta, beta_err) = stats(|f| f.beta);
+
+            GroupFit {
+                network,
+                size,
+                seeds: group.len(),
+                m0,
+                m0_err,
+                tc,
+                tc_err,
+                beta,
+                beta_err,
+            }
+        })
+        .collect()
+}
+
+/// Writes the per seed and aggregated fits as `{out}.json` and `{out}.csv`,
+/// aggregated rows having an empty seed.
+pub fn save(out: &str, fits: &[SeedFit], groups: &[GroupFit]) -> Result<(String, String), Box<dyn Error>> {
+    let json_path = format!("{}.json", out);
+    let csv_path = format!("{}.csv", out);
+
+    if let Some(dir) = Path::new(out).parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(
+        &json_path,
+        serde_json::to_string(&serde_json::json!({ "seeds": fits, "groups": groups }))?,
+    )?;
+
+    let mut writer = Writer::from_path(&csv_path)?;
+    writer.write_record([
+        "network", "size", "seed", "points", "M0", "M0_err", "Tc", "Tc_err", "beta", "beta_err", "chi2",
+    ])?;
+
+    for f in fits {
+        let p = &f.fit;
+        writer.serialize((
+            &f.network, f.size, f.seed, p.points, p.m0, p.m0_err, p.tc, p.tc_err, p.beta, p.beta_err, p.chi2,
+        ))?;
+    }
+
+    for g in groups {
+        writer.serialize((
+            &g.network, g.size, "", g.seeds, g.m0, g.m0_err, g.tc, g.tc_err, g.beta, g.beta_err, "",
+        ))?;
+    }
+
+    writer.flush()?;
+
+    Ok((json_path, csv_path))
+}
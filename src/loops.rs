@@ -0,0 +1,151 @@
+use std::{error::Error, path::Path};
+
+use csv::Reader;
+use serde::Serialize;
+
+/// Shape of the closed hysteresis loop traced by `simulate_hysteresis`, read
+/// back from its csv. The virgin curve up to the first H_max is skipped; the
+/// loop is the descending branch H_max → H_min followed by the ascending
+/// branch back up. Anything the run didn't reach is NaN.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LoopMetrics {
+    /// H where M crosses 0 on the descending branch
+    pub coercive_down: f64,
+    /// H where M crosses 0 on the ascending branch
+    pub coercive_up: f64,
+    /// M at H = 0 on the descending branch
+    pub remanence_down: f64,
+    /// M at H = 0 on the ascending branch
+    pub remanence_up: f64,
+    /// (M(H_max) - M(H_min)) / 2
+    pub saturation: f64,
+    /// ∮ H dM, the energy per spin dissipated over one cycle
+    pub area: f64,
+    /// horizontal shift of the loop, (H_c+ + H_c-) / 2
+    pub field_asymmetry: f64,
+    /// vertical shift of the loop, (M_r+ + M_r-) / 2
+    pub mag_asymmetry: f64,
+}
+
+/// Linear interpolation of y at the first point where x crosses `at`.
+fn crossing(branch: &[(f64, f64)], at: f64, x: fn(&(f64, f64)) -> f64, y: fn(&(f64, f64)) -> f64) -> f64 {
+    for w in branch.windows(2) {
+        let (x0, x1) = (x(&w[0]) - at, x(&w[1]) - at);
+
+        if x0 == 0. {
+            return y(&w[0]);
+        }
+
+        if x0 * x1 <= 0. {
+            return y(&w[0]) + (y(&w[1]) - y(&w[0])) * x0 / (x0 - x1);
+        }
+    }
+
+    f64::NAN
+}
+
+impl LoopMetrics {
+    const COUNT: usize = 8;
+
+    /// `points` are the (H, M) rows in the order they were simulated.
+    pub fn of_points(points: &[(f64, f64)]) -> Self {
+        let h_max = points.iter().fold(f64::NEG_INFINITY, |u, p| u.max(p.0));
+        let h_min = points.iter().fold(f64::INFINITY, |u, p| u.min(p.0));
+
+        let top = points.iter().position(|p| p.0 == h_max).unwrap_or(points.len());
+        let bottom = top + points[top..].iter().position(|p| p.0 == h_min).unwrap_or(points.len() - top);
+
+        if bottom + 1 >= points.len() {
+            return LoopMetrics::from_values([f64::NAN; LoopMetrics::COUNT]);
+        }
+
+        // the run stops one step short of coming back to H_max
+        let end = bottom + points[bottom..].iter().position(|p| p.0 == h_max).unwrap_or(points.len() - 1 - bottom);
+        let down = &points[top..=bottom];
+        let up = &points[bottom..=end];
+        let h = |p: &(f64, f64)| p.0;
+        let m = |p: &(f64, f64)| p.1;
+
+        let coercive_down = crossing(down, 0., m, h);
+        let coercive_up = crossing(up, 0., m, h);
+        let remanence_down = crossing(down, 0., h, m);
+        let remanence_up = crossing(up, 0., h, m);
+
+        // trapezoids around the path, closed back to the top, give ∮ M dH = -∮ H dM
+        let area = -points[top..=end]
+            .iter()
+            .chain(std::iter::once(&points[top]))
+            .collect::<Vec<_>>()
+            .windows(2)
+            .map(|w| (w[0].1 + w[1].1) * (w[1].0 - w[0].0) / 2.)
+            .sum::<f64>();
+
+        LoopMetrics {
+            coercive_down,
+            coercive_up,
+            remanence_down,
+            remanence_up,
+            saturation: (points[top].1 - points[bottom].1) / 2.,
+            area,
+            field_asymmetry: (coercive_down + coercive_up) / 2.,
+            mag_asymmetry: (remanence_down + remanence_up) / 2.,
+        }
+    }
+
+    /// Metrics of the loop in a hysteresis csv, traced by the per-sweep
+    /// average aM, or by the last-sweep M for runs that predate the column.
+    pub fn of_csv(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut reader = Reader::from_path(path)?;
+        let headers = reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h == name).ok_or(format!("no {} column", name));
+        let (h, m) = (column("H")?, column("aM").or_else(|_| column("M"))?);
+
+        let mut points = vec![];
+        for record in reader.records() {
+            let record = record?;
+            points.push((record[h].trim().parse::<f64>()?, record[m].trim().parse::<f64>()?));
+        }
+
+        Ok(LoopMetrics::of_points(&points))
+    }
+
+    fn values(&self) -> [f64; LoopMetrics::COUNT] {
+        [
+            self.coercive_down,
+            self.coercive_up,
+            self.remanence_down,
+            self.remanence_up,
+            self.saturation,
+            self.area,
+            self.field_asymmetry,
+            self.mag_asymmetry,
+        ]
+    }
+
+    fn from_values(v: [f64; LoopMetrics::COUNT]) -> Self {
+        LoopMetrics {
+            coercive_down: v[0],
+            coercive_up: v[1],
+            remanence_down: v[2],
+            remanence_up: v[3],
+            saturation: v[4],
+            area: v[5],
+            field_asymmetry: v[6],
+            mag_asymmetry: v[7],
+        }
+    }
+
+    /// Mean over seeds and its standard error, NaN for a single seed.
+    pub fn aggregate(metrics: &[LoopMetrics]) -> (LoopMetrics, LoopMetrics) {
+        let n = metrics.len() as f64;
+        let mean: [f64; LoopMetrics::COUNT] =
+            std::array::from_fn(|k| metrics.iter().map(|m| m.values()[k]).sum::<f64>() / n);
+        let error: [f64; LoopMetrics::COUNT] = std::array::from_fn(|k| {
+            let var = metrics.iter().map(|m| (m.values()[k] - mean[k]).powi(2)).sum::<f64>() / (n - 1.);
+
+            (var / n).sqrt()
+        });
+
+        (LoopMetrics::from_values(mean), LoopMetrics::from_values(error))
+    }
+}
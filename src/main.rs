@@ -1,10 +1,29 @@
+mod annealing;
+mod autocorrelation;
+mod checkpoint;
 mod child;
 mod cli;
+mod correlation;
+mod demon;
 mod descriptor;
+mod domains;
+mod equilibrium;
+mod errors;
+mod exact;
+mod fit;
+mod loops;
 mod matrix;
+mod multispin;
 mod network;
+mod reference;
+mod reweight;
+mod scaling;
+mod nfold;
 mod simulation;
-mod mathy;
+mod structure;
+mod tempering;
+mod theory;
+mod wang_landau;
 
 use std::sync::mpsc::{self, Sender};
 use std::{thread};
@@ -14,13 +33,30 @@ use std::{env, error::Error, fs, path::Path};
 use child::ChildMsg;
 use clap::*;
 use cli::ArgsHysteresis;
-use descriptor::{Descriptor, HysteresisDescriptor, PhaseDescriptor};
-use network::NetworkType;
+use annealing::{Annealing, Schedule, ScheduleKind};
+use demon::{Demon, DemonConfig};
+use checkpoint::Checkpoint;
+use equilibrium::Criterion;
+use descriptor::{
+    AnnealingDescriptor, DemonDescriptor, Descriptor, ExactDescriptor, HysteresisDescriptor, LoopsDescriptor,
+    PhaseDescriptor, TemperingDescriptor, WangLandauDescriptor,
+};
+use exact::{Exact, ExactConfig};
+use loops::LoopMetrics;
+use multispin::MultiSpin;
+use nfold::NFold;
+use network::{Network, NetworkType};
 use rand::SeedableRng;
+use reference::Reference;
 use simulation::{Simulation, SimulationConfig};
+use tempering::{Tempering, TemperingConfig};
+use theory::Theory;
+use wang_landau::{DensityOfStates, ThermoConfig, WangLandau, WangLandauConfig};
 
 use crate::child::Child;
-use crate::cli::{ArgError, ArgsPhase};
+use crate::cli::{
+    ArgError, ArgsAnnealing, ArgsDemon, ArgsExact, ArgsPhase, ArgsTempering, ArgsWangLandau,
+};
 
 // add extra params, split into two
 
@@ -41,6 +77,10 @@ fn make_data_path_phase(
     )
 }
 
+/// Directory of one seed of a hysteresis run, from its network, size, step,
+/// max, temperature and seed.
+type MakeDataPath = fn(NetworkType, usize, f64, f64, f64, u64) -> String;
+
 fn make_data_path_hys(
     network_type: NetworkType,
     size: usize,
@@ -60,6 +100,113 @@ fn make_data_path_hys(
     )
 }
 
+fn make_data_path_nfold(
+    network_type: NetworkType,
+    size: usize,
+    step: f64,
+    max: f64,
+    temp: f64,
+    seed: u64,
+) -> String {
+    format!(
+        "data/{}/nfold/size={}_step={}_max={}_temp={}_seed={}",
+        network_type.to_string(),
+        size,
+        step,
+        max,
+        temp,
+        seed
+    )
+}
+
+fn make_data_path_tempering(
+    network_type: NetworkType,
+    size: usize,
+    replicas: usize,
+    min: f64,
+    max: f64,
+    seed: u64,
+) -> String {
+    format!(
+        "data/{}/temper/size={}_replicas={}_min={}_max={}_seed={}",
+        network_type.to_string(),
+        size,
+        replicas,
+        min,
+        max,
+        seed
+    )
+}
+
+fn make_data_path_wang_landau(
+    network_type: NetworkType,
+    size: usize,
+    ln_f_final: f64,
+    flatness: f64,
+    seed: u64,
+) -> String {
+    format!(
+        "data/{}/wl/size={}_lnf={}_flat={}_seed={}",
+        network_type.to_string(),
+        size,
+        ln_f_final,
+        flatness,
+        seed
+    )
+}
+
+fn make_data_path_annealing(
+    network_type: NetworkType,
+    size: usize,
+    schedule: ScheduleKind,
+    stages: usize,
+    sweeps: usize,
+    seed: u64,
+) -> String {
+    format!(
+        "data/{}/anneal/size={}_schedule={:?}_stages={}_sweeps={}_seed={}",
+        network_type.to_string(),
+        size,
+        schedule,
+        stages,
+        sweeps,
+        seed
+    )
+}
+
+fn make_data_path_multispin(size: usize, step: f64, max: f64, seed: u64) -> String {
+    format!(
+        "data/regular/msc/size={}_step={}_max={}_seed={}",
+        size, step, max, seed
+    )
+}
+
+fn make_data_path_exact(network_type: NetworkType, size: usize, seed: u64) -> String {
+    format!(
+        "data/{}/exact/size={}_seed={}",
+        network_type.to_string(),
+        size,
+        seed
+    )
+}
+
+fn make_data_path_demon(
+    network_type: NetworkType,
+    size: usize,
+    step: f64,
+    per_site: bool,
+    seed: u64,
+) -> String {
+    format!(
+        "data/{}/demon/size={}_step={}_demons={}_seed={}",
+        network_type.to_string(),
+        size,
+        step,
+        if per_site { "site" } else { "single" },
+        seed
+    )
+}
+
 fn prepare_data_path(data_dir: &String) -> Result<String, Box<dyn Error>> {
     let data_path_str = format!("{}/data.csv", data_dir);
 
@@ -84,30 +231,542 @@ fn save<D: Descriptor>(
 
     desc.save(&desc_path_str)?;
 
-    send!(
-        final tx,
+    send!(
+        final tx,
+        name,
+        desc_path_str.to_owned()
+    );
+
+    thread::sleep(Duration::from_millis(5));
+
+    Ok(desc_path_str)
+}
+
+fn run_phase(
+    rand_seed: u64,
+    args: &ArgsPhase,
+    network_type: NetworkType,
+    eq_steps: usize,
+    s0: f64,
+    tx: Sender<ChildMsg>,
+    name: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+
+    let data_dir_str =
+        make_data_path_phase(network_type, args.size, args.t_step, args.t_max, rand_seed);
+    let data_path_str = prepare_data_path(&data_dir_str)?;
+    let data_path = Path::new(&data_path_str);
+
+    let mut s = Simulation::new(
+        args.size,
+        SimulationConfig {
+            temp: args.t_min,
+            h: 0f64,
+            j: 1f64,
+            kb: 1f64,
+            equilibrium_steps: eq_steps,
+            network_type: network_type,
+            eq_threshold: args.eq_threshold.unwrap_or_else(|| eq_threshold_of_type(network_type)),
+            criterion: args.criterion,
+            threads: args.threads,
+        },
+        &mut rand,
+        name,
+        tx,
+        data_dir_str.to_owned()
+    );
+
+    let config = simulation::PhaseConfig {
+        t_min: args.t_min,
+        t_max: args.t_max,
+        t_step: args.t_step,
+        s0,
+        past_max: args.past_max,
+        correlation: !args.no_correlation,
+        measure: simulation::MeasureConfig {
+            samples: args.samples,
+            interval: args.interval,
+            ess: args.ess,
+            max_sweeps: args.max_sweeps,
+            series: !args.no_series,
+        },
+    };
+
+    let theory_path_str = format!("{}/theory.csv", data_dir_str);
+    let theory_path = Path::new(&theory_path_str);
+    Theory::new(&s.network, s.config.j, s.config.kb).save_phase(theory_path, &config)?;
+
+    match s.simulate_phase(data_path, config, &mut rand) {
+        Ok(_) => {
+            let desc = PhaseDescriptor {
+                config: args,
+                network_type,
+                lattice: s.network.lattice,
+                seed: rand_seed,
+                deg_avg: s.network.deg_avg,
+                deg_mse: s.network.deg_mse,
+                data_path: data_path,
+                theory_path,
+                columns: simulation::column_definitions(&simulation::PHASE_COLUMNS),
+            };
+
+            save(&data_dir_str, &desc, &s.tx, &s.name)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Runs one hysteresis loop, from scratch or from the checkpoint in `resume`
+/// along with the directory it was found in, which the run carries on in.
+fn run_hysteresis(
+    rand_seed: u64,
+    args: &ArgsHysteresis,
+    network_type: NetworkType,
+    temp: f64,
+    tx: Sender<ChildMsg>,
+    name: String,
+    resume: Option<(String, Checkpoint)>,
+) -> Result<String, Box<dyn Error>> {
+    let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+
+    let (data_dir_str, resume) = match resume {
+        Some((dir, checkpoint)) => (dir, Some(checkpoint)),
+        None => (
+            make_data_path_hys(network_type, args.size, args.h_step, args.h_max, temp, rand_seed),
+            None,
+        ),
+    };
+    let data_dir_str = &data_dir_str;
+    let data_path_str = match resume {
+        Some(_) => format!("{}/data.csv", data_dir_str),
+        None => prepare_data_path(data_dir_str)?,
+    };
+    let data_path = Path::new(&data_path_str);
+
+    let mut s = Simulation::new(
+        args.size,
+        SimulationConfig {
+            temp,
+            h: 0f64,
+            j: 1f64,
+            kb: 1f64,
+            equilibrium_steps: args.eq_steps,
+            network_type: network_type,
+            eq_threshold: args.eq_threshold.unwrap_or_else(|| eq_threshold_of_type(network_type)),
+            criterion: args.criterion,
+            threads: args.threads,
+        },
+        &mut rand,
+        name,
+        tx,
+        data_dir_str.to_owned()
+    );
+
+    let config = simulation::HysteresisConfig {
+        h_min: -args.h_max,
+        h_max: args.h_max,
+        h_step: args.h_step,
+        measure: simulation::MeasureConfig {
+            samples: args.samples,
+            interval: args.interval,
+            ess: args.ess,
+            max_sweeps: args.max_sweeps,
+            series: !args.no_series,
+        },
+        checkpoint_every: args.checkpoint_every,
+        run: serde_json::json!({
+            "args": args,
+            "network_type": network_type,
+            "seed": rand_seed,
+            "temp": temp,
+        }),
+    };
+
+    let theory_path_str = format!("{}/theory.csv", data_dir_str);
+    let theory_path = Path::new(&theory_path_str);
+    Theory::new(&s.network, s.config.j, s.config.kb).save_hysteresis(theory_path, temp, &config)?;
+
+    match s.simulate_hysteresis(&data_path, config, &mut rand, resume) {
+        Ok(_) => {
+            let desc = HysteresisDescriptor {
+                config: args,
+                network_type,
+                lattice: s.network.lattice,
+                seed: rand_seed,
+                temp,
+                deg_avg: s.network.deg_avg,
+                deg_mse: s.network.deg_mse,
+                data_path: data_path,
+                theory_path,
+                metrics: LoopMetrics::of_csv(data_path)?,
+                columns: simulation::column_definitions(&simulation::HYSTERESIS_COLUMNS),
+            };
+
+            save(&data_dir_str, &desc, &s.tx, &s.name)
+        }
+
+        Err(e) => Err(e),
+    }
+}
+
+fn run_nfold(
+    rand_seed: u64,
+    args: &ArgsHysteresis,
+    network_type: NetworkType,
+    temp: f64,
+    tx: Sender<ChildMsg>,
+    name: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+
+    let data_dir_str = make_data_path_nfold(
+        network_type,
+        args.size,
+        args.h_step,
+        args.h_max,
+        temp,
+        rand_seed,
+    );
+    let data_path_str = prepare_data_path(&data_dir_str)?;
+    let data_path = Path::new(&data_path_str);
+
+    let mut s = Simulation::new(
+        args.size,
+        SimulationConfig {
+            temp,
+            h: 0f64,
+            j: 1f64,
+            kb: 1f64,
+            equilibrium_steps: args.eq_steps,
+            network_type,
+            eq_threshold: args.eq_threshold.unwrap_or_else(|| eq_threshold_of_type(network_type)),
+            criterion: args.criterion,
+            threads: 0,
+        },
+        &mut rand,
+        name,
+        tx,
+        data_dir_str.to_owned(),
+    );
+    let mut nfold = NFold::new(&s);
+
+    let config = simulation::HysteresisConfig {
+        h_min: -args.h_max,
+        h_max: args.h_max,
+        h_step: args.h_step,
+        measure: simulation::MeasureConfig {
+            samples: args.samples,
+            interval: args.interval,
+            ess: args.ess,
+            max_sweeps: args.max_sweeps,
+            series: !args.no_series,
+        },
+        checkpoint_every: 0,
+        run: serde_json::Value::Null,
+    };
+
+    let theory_path_str = format!("{}/theory.csv", data_dir_str);
+    let theory_path = Path::new(&theory_path_str);
+    Theory::new(&s.network, s.config.j, s.config.kb).save_hysteresis(theory_path, temp, &config)?;
+
+    match nfold.simulate_hysteresis(&mut s, data_path, config, &mut rand) {
+        Ok(_) => {
+            let desc = HysteresisDescriptor {
+                config: args,
+                network_type,
+                lattice: s.network.lattice,
+                seed: rand_seed,
+                temp,
+                deg_avg: s.network.deg_avg,
+                deg_mse: s.network.deg_mse,
+                data_path,
+                theory_path,
+                metrics: LoopMetrics::of_csv(data_path)?,
+                columns: simulation::column_definitions(&simulation::HYSTERESIS_COLUMNS),
+            };
+
+            save(&data_dir_str, &desc, &s.tx, &s.name)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Aggregates the loop metrics of every seed of a hysteresis (or n-fold) run
+/// of `args`, one file per network and temperature next to the seed
+/// directories under `root`; skips those some seed hasn't finished yet.
+/// Returns the paths written.
+fn save_loops(
+    args: &ArgsHysteresis,
+    root: &str,
+    make_data_path: MakeDataPath,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut paths = vec![];
+
+    for network_type in [NetworkType::Regular, NetworkType::Irregular] {
+        for &temp in args.temps.iter() {
+            let dirs: Vec<String> = args
+                .seeds
+                .iter()
+                .map(|&seed| {
+                    let dir = make_data_path(network_type, args.size, args.h_step, args.h_max, temp, seed);
+                    format!("{}{}", root, dir)
+                })
+                .collect();
+
+            if !dirs.iter().all(|dir| Path::new(dir).join("desc.json").exists()) {
+                continue;
+            }
+
+            let metrics = dirs
+                .iter()
+                .map(|dir| LoopMetrics::of_csv(Path::new(&format!("{}/data.csv", dir))))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let (prefix, _) = match dirs.first().and_then(|dir| dir.rsplit_once("_seed=")) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let (mean, error) = LoopMetrics::aggregate(&metrics);
+            let desc = LoopsDescriptor {
+                config: args,
+                temp,
+                seeds: &args.seeds,
+                metrics,
+                mean,
+                error,
+            };
+
+            let path = format!("{}_loops.json", prefix);
+            desc.save(&path)?;
+            paths.push(path);
+        }
+    }
+
+    Ok(paths)
+}
+
+fn run_tempering(
+    rand_seed: u64,
+    args: &ArgsTempering,
+    network_type: NetworkType,
+    tx: Sender<ChildMsg>,
+    name: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+
+    let data_dir_str = make_data_path_tempering(
+        network_type,
+        args.size,
+        args.replicas,
+        args.t_min,
+        args.t_max,
+        rand_seed,
+    );
+    let data_path_str = prepare_data_path(&data_dir_str)?;
+    let data_path = Path::new(&data_path_str);
+    let swaps_path_str = format!("{}/swaps.csv", data_dir_str);
+    let swaps_path = Path::new(&swaps_path_str);
+
+    let s = Simulation::new(
+        args.size,
+        SimulationConfig {
+            temp: args.t_min,
+            h: 0f64,
+            j: 1f64,
+            kb: 1f64,
+            equilibrium_steps: args.eq_sweeps,
+            network_type,
+            eq_threshold: eq_threshold_of_type(network_type),
+            criterion: Criterion::Fixed,
+            threads: 0,
+        },
+        &mut rand,
+        name,
+        tx,
+        data_dir_str.to_owned(),
+    );
+
+    let config = TemperingConfig {
+        t_min: args.t_min,
+        t_max: args.t_max,
+        replicas: args.replicas,
+        eq_sweeps: args.eq_sweeps,
+        sweeps: args.sweeps,
+        swap_interval: args.swap_interval,
+    };
+    let mut tempering = Tempering::new(&s, &config, &mut rand);
+
+    match tempering.simulate(data_path, swaps_path, &config, &mut rand) {
+        Ok(_) => {
+            let desc = TemperingDescriptor {
+                config: args,
+                lattice: s.network.lattice,
+                seed: rand_seed,
+                deg_avg: s.network.deg_avg,
+                deg_mse: s.network.deg_mse,
+                temps: tempering.replicas.iter().map(|r| r.config.temp).collect(),
+                data_path,
+                swaps_path,
+            };
+
+            save(&data_dir_str, &desc, &s.tx, &s.name)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn run_wang_landau(
+    rand_seed: u64,
+    args: &ArgsWangLandau,
+    network_type: NetworkType,
+    tx: Sender<ChildMsg>,
+    name: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+
+    let data_dir_str = make_data_path_wang_landau(
+        network_type,
+        args.size,
+        args.ln_f_final,
+        args.flatness,
+        rand_seed,
+    );
+    prepare_data_path(&data_dir_str)?;
+    let dos_path_str = format!("{}/ln_g.csv", data_dir_str);
+    let dos_path = Path::new(&dos_path_str);
+    let thermo_path_str = format!("{}/thermo.csv", data_dir_str);
+    let thermo_path = Path::new(&thermo_path_str);
+
+    let network = Network::new(args.size, &network_type, &mut rand);
+    let mut wl = WangLandau::new(
+        network,
+        WangLandauConfig {
+            j: 1f64,
+            ln_f_final: args.ln_f_final,
+            flatness: args.flatness,
+            check_sweeps: args.check_sweeps,
+        },
+        name,
+        tx,
+    );
+
+    let dos = wl.simulate(&mut rand);
+    dos.save(dos_path)?;
+    dos.save_thermodynamics(
+        thermo_path,
+        &ThermoConfig {
+            t_min: args.t_min,
+            t_max: args.t_max,
+            t_step: args.t_step,
+            kb: 1f64,
+        },
+    )?;
+
+    let desc = WangLandauDescriptor {
+        config: args,
+        lattice: wl.network.lattice,
+        seed: rand_seed,
+        deg_avg: wl.network.deg_avg,
+        deg_mse: wl.network.deg_mse,
+        dos_path,
+        thermo_path,
+    };
+
+    save(&data_dir_str, &desc, &wl.tx, &wl.name)
+}
+
+/// The schedule of an annealing run, from its kind or its table.
+fn schedule_of(args: &ArgsAnnealing) -> Result<Schedule, Box<dyn Error>> {
+    match (args.schedule, &args.table) {
+        (ScheduleKind::Table, Some(table)) => Schedule::from_table(Path::new(table), args.sweeps),
+        (ScheduleKind::Table, None) => Err("the table schedule needs --table".into()),
+        (kind, _) => Ok(Schedule::new(kind, args.t_start, args.t_end, args.stages, args.sweeps)),
+    }
+}
+
+fn run_annealing(
+    rand_seed: u64,
+    args: &ArgsAnnealing,
+    network_type: NetworkType,
+    tx: Sender<ChildMsg>,
+    name: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+
+    let schedule = schedule_of(args)?;
+
+    let data_dir_str = make_data_path_annealing(
+        network_type,
+        args.size,
+        args.schedule,
+        schedule.temps.len(),
+        args.sweeps,
+        rand_seed,
+    );
+    let data_path_str = prepare_data_path(&data_dir_str)?;
+    let data_path = Path::new(&data_path_str);
+    let best_path_str = format!("{}/best.json", data_dir_str);
+    let best_path = Path::new(&best_path_str);
+
+    let mut s = Simulation::new(
+        args.size,
+        SimulationConfig {
+            temp: args.t_start,
+            h: args.h,
+            j: 1f64,
+            kb: 1f64,
+            equilibrium_steps: args.sweeps,
+            network_type,
+            eq_threshold: eq_threshold_of_type(network_type),
+            criterion: Criterion::Fixed,
+            threads: 0,
+        },
+        &mut rand,
         name,
-        desc_path_str.to_owned()
+        tx,
+        data_dir_str.to_owned(),
     );
 
-    thread::sleep(Duration::from_millis(5));
+    let mut annealing = Annealing::new(&mut s);
 
-    Ok(desc_path_str)
+    match annealing.simulate(&mut s, data_path, &schedule, &mut rand) {
+        Ok(_) => {
+            annealing.save_best(&s, &best_path_str)?;
+
+            let desc = AnnealingDescriptor {
+                config: args,
+                lattice: s.network.lattice,
+                seed: rand_seed,
+                deg_avg: s.network.deg_avg,
+                deg_mse: s.network.deg_mse,
+                temps: &schedule.temps,
+                best_energy: annealing.best_energy,
+                best_time: annealing.best_time,
+                data_path,
+                best_path,
+            };
+
+            save(&data_dir_str, &desc, &s.tx, &s.name)
+        }
+        Err(e) => Err(e),
+    }
 }
 
-fn run_phase(
+/// A phase run like `run_phase` on the regular lattice, swept by the
+/// multi-spin coded engine.
+fn run_multispin(
     rand_seed: u64,
     args: &ArgsPhase,
-    network_type: NetworkType,
     eq_steps: usize,
-    s0: f64,
     tx: Sender<ChildMsg>,
     name: String,
 ) -> Result<String, Box<dyn Error>> {
     let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+    let network_type = NetworkType::Regular;
 
-    let data_dir_str =
-        make_data_path_phase(network_type, args.size, args.t_step, args.t_max, rand_seed);
+    let data_dir_str = make_data_path_multispin(args.size, args.t_step, args.t_max, rand_seed);
     let data_path_str = prepare_data_path(&data_dir_str)?;
     let data_path = Path::new(&data_path_str);
 
@@ -119,33 +778,50 @@ fn run_phase(
             j: 1f64,
             kb: 1f64,
             equilibrium_steps: eq_steps,
-            network_type: network_type,
-            eq_threshold: eq_threshold_of_type(network_type),
+            network_type,
+            eq_threshold: args.eq_threshold.unwrap_or_else(|| eq_threshold_of_type(network_type)),
+            criterion: args.criterion,
+            threads: 0,
         },
         &mut rand,
         name,
         tx,
-        data_dir_str.to_owned()
+        data_dir_str.to_owned(),
     );
+    let msc = MultiSpin::new(&s)?;
 
-    match s.simulate_phase(
-        data_path,
-        simulation::PhaseConfig {
-            t_min: args.t_min,
-            t_max: args.t_max,
-            t_step: args.t_step,
-            s0,
+    let config = simulation::PhaseConfig {
+        t_min: args.t_min,
+        t_max: args.t_max,
+        t_step: args.t_step,
+        s0: -1.,
+        past_max: args.past_max,
+        correlation: !args.no_correlation,
+        measure: simulation::MeasureConfig {
+            samples: args.samples,
+            interval: args.interval,
+            ess: args.ess,
+            max_sweeps: args.max_sweeps,
+            series: !args.no_series,
         },
-        &mut rand,
-    ) {
+    };
+
+    let theory_path_str = format!("{}/theory.csv", data_dir_str);
+    let theory_path = Path::new(&theory_path_str);
+    Theory::new(&s.network, s.config.j, s.config.kb).save_phase(theory_path, &config)?;
+
+    match s.simulate_phase_with(data_path, config, &mut rand, msc) {
         Ok(_) => {
             let desc = PhaseDescriptor {
                 config: args,
+                network_type,
                 lattice: s.network.lattice,
                 seed: rand_seed,
                 deg_avg: s.network.deg_avg,
                 deg_mse: s.network.deg_mse,
-                data_path: data_path,
+                data_path,
+                theory_path,
+                columns: simulation::column_definitions(&simulation::PHASE_COLUMNS),
             };
 
             save(&data_dir_str, &desc, &s.tx, &s.name)
@@ -154,66 +830,108 @@ fn run_phase(
     }
 }
 
-fn run_hysteresis(
+fn run_exact(
     rand_seed: u64,
-    args: &ArgsHysteresis,
+    args: &ArgsExact,
     network_type: NetworkType,
-    temp: f64,
     tx: Sender<ChildMsg>,
     name: String,
 ) -> Result<String, Box<dyn Error>> {
     let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
 
-    let data_dir_str = &make_data_path_hys(
-        network_type,
-        args.size,
-        args.h_step,
-        args.h_max,
-        temp,
-        rand_seed,
-    );
-    let data_path_str = prepare_data_path(data_dir_str)?;
+    let data_dir_str = make_data_path_exact(network_type, args.size, rand_seed);
+    let data_path_str = prepare_data_path(&data_dir_str)?;
+    let data_path = Path::new(&data_path_str);
+
+    // same draws as `Simulation::new`, so the graph matches a simulation run with this seed
+    let network = Network::new(args.size, &network_type, &mut rand);
+
+    send!(tx, name, format!("enumerating 2^{} states", args.size * args.size));
+
+    let exact = Exact::enumerate(&network)?;
+    exact.save(
+        data_path,
+        &ExactConfig {
+            t_min: args.t_min,
+            t_max: args.t_max,
+            t_step: args.t_step,
+            h_min: args.h_min,
+            h_max: args.h_max,
+            h_step: args.h_step,
+            j: 1f64,
+            kb: 1f64,
+        },
+    )?;
+
+    let desc = ExactDescriptor {
+        config: args,
+        lattice: network.lattice,
+        seed: rand_seed,
+        deg_avg: network.deg_avg,
+        deg_mse: network.deg_mse,
+        data_path,
+    };
+
+    save(&data_dir_str, &desc, &tx, &name)
+}
+
+fn run_demon(
+    rand_seed: u64,
+    args: &ArgsDemon,
+    network_type: NetworkType,
+    tx: Sender<ChildMsg>,
+    name: String,
+) -> Result<String, Box<dyn Error>> {
+    let mut rand = rand_chacha::ChaCha20Rng::seed_from_u64(rand_seed);
+
+    let data_dir_str =
+        make_data_path_demon(network_type, args.size, args.e_step, args.per_site, rand_seed);
+    let data_path_str = prepare_data_path(&data_dir_str)?;
     let data_path = Path::new(&data_path_str);
 
     let mut s = Simulation::new(
         args.size,
         SimulationConfig {
-            temp,
+            temp: 1f64,
             h: 0f64,
             j: 1f64,
             kb: 1f64,
-            equilibrium_steps: args.eq_steps,
-            network_type: network_type,
+            equilibrium_steps: args.eq_sweeps,
+            network_type,
             eq_threshold: eq_threshold_of_type(network_type),
+            criterion: Criterion::Fixed,
+            threads: 0,
         },
         &mut rand,
         name,
         tx,
-        data_dir_str.to_owned()
+        data_dir_str.to_owned(),
     );
+    let mut demon = Demon::new(&s, args.per_site);
 
-    match s.simulate_hysteresis(
-        &data_path,
-        simulation::HysteresisConfig {
-            h_min: -args.h_max,
-            h_max: args.h_max,
-            h_step: args.h_step,
+    match demon.simulate_phase(
+        &mut s,
+        data_path,
+        DemonConfig {
+            e_step: args.e_step,
+            e_max: args.e_max,
+            eq_sweeps: args.eq_sweeps,
+            sweeps: args.sweeps,
         },
         &mut rand,
     ) {
         Ok(_) => {
-            let desc = HysteresisDescriptor {
+            let desc = DemonDescriptor {
                 config: args,
                 lattice: s.network.lattice,
                 seed: rand_seed,
                 deg_avg: s.network.deg_avg,
                 deg_mse: s.network.deg_mse,
-                data_path: data_path,
+                data_path,
             };
 
             save(&data_dir_str, &desc, &s.tx, &s.name)
         }
-
         Err(e) => Err(e),
     }
 }
@@ -222,9 +940,13 @@ fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
     let mut children = vec![];
     let (tx, rx) = mpsc::channel::<ChildMsg>();
+    // what to aggregate loops of once the children are done
+    let mut loops_of: Option<(ArgsHysteresis, String, MakeDataPath)> = None;
 
     let result: Result<String, Box<dyn Error>> = match args.get(1) {
         Some(simulation_type) if simulation_type.as_str() == "hys" => {
+            equilibrium::check_hysteresis(cli::ArgsHysteresis::parse_from(env::args().skip(1)).criterion)?;
+
             for network_type in vec![NetworkType::Regular, NetworkType::Irregular] {
                 let args = cli::ArgsHysteresis::parse_from(env::args().skip(1));
 
@@ -239,13 +961,46 @@ fn main() -> Result<(), Box<dyn Error>> {
                         let tx_ = tx.clone();
 
                         children.push(Child::make(&name.to_owned(), move || {
-                            run_hysteresis(seed, &args, network_type, temp.to_owned(), tx_, name)
+                            run_hysteresis(seed, &args, network_type, temp.to_owned(), tx_, name, None)
                                 .unwrap();
                         }));
                     }
                 }
             }
 
+            loops_of = Some((
+                cli::ArgsHysteresis::parse_from(env::args().skip(1)),
+                String::new(),
+                make_data_path_hys,
+            ));
+
+            print!("{} ", simulation_type);
+
+            Ok("".to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "resume" => {
+            let args = cli::ArgsResume::parse_from(env::args().skip(1));
+            let checkpoint = Checkpoint::load(&args.dir)?;
+            let run = &checkpoint.run;
+
+            let hys_args: ArgsHysteresis = serde_json::from_value(run["args"].clone())?;
+            let network_type: NetworkType = serde_json::from_value(run["network_type"].clone())?;
+            let seed = run["seed"].as_u64().ok_or("checkpoint has no seed")?;
+            let temp = run["temp"].as_f64().ok_or("checkpoint has no temperature")?;
+
+            // the seed directories of the run sit under the same root as this one
+            let own = make_data_path_hys(network_type, hys_args.size, hys_args.h_step, hys_args.h_max, temp, seed);
+            if let Some(root) = args.dir.trim_end_matches('/').strip_suffix(&own) {
+                loops_of = Some((serde_json::from_value(run["args"].clone())?, root.to_string(), make_data_path_hys));
+            }
+
+            let name = format!("{}, seed={}, T={}", network_type.to_string(), seed, temp);
+            let tx_ = tx.clone();
+
+            children.push(Child::make(&name.to_owned(), move || {
+                run_hysteresis(seed, &hys_args, network_type, temp, tx_, name, Some((args.dir, checkpoint))).unwrap();
+            }));
+
             print!("{} ", simulation_type);
 
             Ok("".to_string())
@@ -273,6 +1028,340 @@ fn main() -> Result<(), Box<dyn Error>> {
 
             Ok(simulation_type.to_string())
         }
+        Some(simulation_type) if simulation_type.as_str() == "nfold" => {
+            equilibrium::check_hysteresis(cli::ArgsHysteresis::parse_from(env::args().skip(1)).criterion)?;
+
+            for network_type in [NetworkType::Regular, NetworkType::Irregular] {
+                let args = cli::ArgsHysteresis::parse_from(env::args().skip(1));
+
+                for seed in args.seeds.into_iter() {
+                    for &temp in args.temps.iter() {
+                        let args = cli::ArgsHysteresis::parse_from(env::args().skip(1));
+
+                        let name =
+                            format!("{}, seed={}, T={}", network_type.to_string(), seed, temp);
+                        let tx_ = tx.clone();
+
+                        children.push(Child::make(&name.to_owned(), move || {
+                            run_nfold(seed, &args, network_type, temp, tx_, name).unwrap();
+                        }));
+                    }
+                }
+            }
+
+            loops_of = Some((
+                cli::ArgsHysteresis::parse_from(env::args().skip(1)),
+                String::new(),
+                make_data_path_nfold,
+            ));
+
+            Ok(simulation_type.to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "temper" => {
+            for network_type in [NetworkType::Regular, NetworkType::Irregular] {
+                let args = cli::ArgsTempering::parse_from(env::args().skip(1));
+
+                for rand_seed in args.seeds {
+                    let args = cli::ArgsTempering::parse_from(env::args().skip(1));
+
+                    let tx_ = tx.clone();
+                    let name = format!("{}, {}", network_type.to_string(), rand_seed);
+
+                    children.push(Child::make(&name.to_owned(), move || {
+                        run_tempering(rand_seed, &args, network_type, tx_, name).unwrap();
+                    }));
+                }
+            }
+
+            Ok(simulation_type.to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "wl" => {
+            let args = cli::ArgsWangLandau::parse_from(env::args().skip(1));
+            wang_landau::check_config(args.flatness, args.check_sweeps)?;
+
+            for network_type in [NetworkType::Regular, NetworkType::Irregular] {
+                let args = cli::ArgsWangLandau::parse_from(env::args().skip(1));
+
+                for rand_seed in args.seeds {
+                    let args = cli::ArgsWangLandau::parse_from(env::args().skip(1));
+
+                    let tx_ = tx.clone();
+                    let name = format!("{}, {}", network_type.to_string(), rand_seed);
+
+                    children.push(Child::make(&name.to_owned(), move || {
+                        run_wang_landau(rand_seed, &args, network_type, tx_, name).unwrap();
+                    }));
+                }
+            }
+
+            Ok(simulation_type.to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "anneal" => {
+            schedule_of(&cli::ArgsAnnealing::parse_from(env::args().skip(1)))?;
+
+            for network_type in [NetworkType::Regular, NetworkType::Irregular] {
+                let args = cli::ArgsAnnealing::parse_from(env::args().skip(1));
+
+                for rand_seed in args.seeds {
+                    let args = cli::ArgsAnnealing::parse_from(env::args().skip(1));
+
+                    let tx_ = tx.clone();
+                    let name = format!("{}, {}", network_type.to_string(), rand_seed);
+
+                    children.push(Child::make(&name.to_owned(), move || {
+                        run_annealing(rand_seed, &args, network_type, tx_, name).unwrap();
+                    }));
+                }
+            }
+
+            Ok(simulation_type.to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "msc" => {
+            let args = cli::ArgsPhase::parse_from(env::args().skip(1));
+
+            for rand_seed in args.seeds {
+                let args = cli::ArgsPhase::parse_from(env::args().skip(1));
+
+                for eq_steps in args.eq_steps {
+                    let args = cli::ArgsPhase::parse_from(env::args().skip(1));
+
+                    let tx_ = tx.clone();
+                    let name = format!("{}, {}", NetworkType::Regular.to_string(), rand_seed);
+
+                    children.push(Child::make(&name.to_owned(), move || {
+                        run_multispin(rand_seed, &args, eq_steps, tx_, name).unwrap();
+                    }));
+                }
+            }
+
+            Ok(simulation_type.to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "exact" => {
+            exact::check_size(cli::ArgsExact::parse_from(env::args().skip(1)).size)?;
+
+            for network_type in [NetworkType::Regular, NetworkType::Irregular] {
+                let args = cli::ArgsExact::parse_from(env::args().skip(1));
+
+                for rand_seed in args.seeds {
+                    let args = cli::ArgsExact::parse_from(env::args().skip(1));
+
+                    let tx_ = tx.clone();
+                    let name = format!("{}, {}", network_type.to_string(), rand_seed);
+
+                    children.push(Child::make(&name.to_owned(), move || {
+                        run_exact(rand_seed, &args, network_type, tx_, name).unwrap();
+                    }));
+                }
+            }
+
+            Ok(simulation_type.to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "demon" => {
+            for network_type in [NetworkType::Regular, NetworkType::Irregular] {
+                let args = cli::ArgsDemon::parse_from(env::args().skip(1));
+
+                for rand_seed in args.seeds {
+                    let args = cli::ArgsDemon::parse_from(env::args().skip(1));
+
+                    let tx_ = tx.clone();
+                    let name = format!("{}, {}", network_type.to_string(), rand_seed);
+
+                    children.push(Child::make(&name.to_owned(), move || {
+                        run_demon(rand_seed, &args, network_type, tx_, name).unwrap();
+                    }));
+                }
+            }
+
+            Ok(simulation_type.to_string())
+        }
+        Some(simulation_type) if simulation_type.as_str() == "compare" => {
+            let args = cli::ArgsCompare::parse_from(env::args().skip(1));
+            let reference = match args.strip {
+                Some(width) => Reference::Strip(width),
+                None => Reference::Onsager,
+            };
+
+            print!("{} ", simulation_type);
+
+            for path_str in args.paths.iter() {
+                let (out_path, dev) = reference::compare(Path::new(path_str), reference)?;
+
+                eprintln!(
+                    "[{}]: {:?} points: {}, M points: {}, rms ΔM: {:.5}, max ΔM: {:.5}, rms ΔE: {:.5}, max ΔE: {:.5}",
+                    path_str,
+                    reference,
+                    dev.points,
+                    dev.mag_points,
+                    dev.rms_mag,
+                    dev.max_mag,
+                    dev.rms_energy,
+                    dev.max_energy
+                );
+                print!("{} ", out_path.display());
+            }
+
+            return Ok(());
+        }
+        Some(simulation_type) if simulation_type.as_str() == "fit" => {
+            let args = cli::ArgsFit::parse_from(env::args().skip(1));
+
+            print!("{} ", simulation_type);
+
+            let mut fits = vec![];
+            for path_str in args.paths.iter() {
+                let f = fit::fit_phase(Path::new(path_str))?;
+
+                eprintln!(
+                    "[{}]: points: {}, M0: {:.5} ± {:.5}, Tc: {:.5} ± {:.5}, β: {:.5} ± {:.5}",
+                    path_str, f.fit.points, f.fit.m0, f.fit.m0_err, f.fit.tc, f.fit.tc_err, f.fit.beta, f.fit.beta_err
+                );
+                fits.push(f);
+            }
+
+            let groups = fit::aggregate(&fits);
+            for g in groups.iter() {
+                eprintln!(
+                    "[{} size={}]: seeds: {}, M0: {:.5} ± {:.5}, Tc: {:.5} ± {:.5}, β: {:.5} ± {:.5}",
+                    g.network, g.size, g.seeds, g.m0, g.m0_err, g.tc, g.tc_err, g.beta, g.beta_err
+                );
+            }
+
+            let (json_path, csv_path) = fit::save(&args.out, &fits, &groups)?;
+            print!("{} {} ", json_path, csv_path);
+
+            return Ok(());
+        }
+        Some(simulation_type) if simulation_type.as_str() == "fss" => {
+            let args = cli::ArgsScaling::parse_from(env::args().skip(1));
+            let config = scaling::ScalingConfig { window: args.window };
+
+            print!("{} ", simulation_type);
+
+            if let Some(dir) = Path::new(&args.out).parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            let collapse_path = format!("{}_collapse.csv", args.out);
+            let mut writer = csv::Writer::from_path(&collapse_path)?;
+            writer.write_record(["network", "L", "T", "x", "U4", "M_scaled", "X_scaled"])?;
+
+            let mut results = vec![];
+            for (network, curves) in scaling::load(&args.paths)? {
+                match scaling::Scaling::of_curves(&network, &curves, config) {
+                    Some(r) => {
+                        eprintln!(
+                            "[{}]: sizes: {:?}, Tc: {:.5} ± {:.5}, ν: {:.4}, β/ν: {:.4}, γ/ν: {:.4}, S: {:.3e} {:.3e} {:.3e}",
+                            network,
+                            r.sizes,
+                            r.tc,
+                            r.tc_err,
+                            r.nu,
+                            r.beta_nu,
+                            r.gamma_nu,
+                            r.residual_binder,
+                            r.residual_mag,
+                            r.residual_susceptibility
+                        );
+
+                        r.save_collapse(&mut writer, &curves)?;
+                        results.push(r);
+                    }
+                    None => eprintln!("[{}]: needs two sizes whose Binder cumulants cross", network),
+                }
+            }
+
+            let json_path = format!("{}.json", args.out);
+            fs::write(&json_path, serde_json::to_string(&results)?)?;
+            print!("{} {} ", json_path, collapse_path);
+
+            return Ok(());
+        }
+        Some(simulation_type) if simulation_type.as_str() == "reweight" => {
+            let args = cli::ArgsReweight::parse_from(env::args().skip(1));
+            let series = reweight::Series::load(&args.paths, 1f64)?;
+            let (temps, fields) = series.points();
+
+            let grid = |name: &str, min: Option<f64>, max: Option<f64>, step: f64, simulated: Vec<f64>| match (min, max) {
+                (Some(min), Some(max)) => Ok((0..=((max - min) / step).round() as usize)
+                    .map(|i| min + i as f64 * step)
+                    .collect()),
+                (None, None) => Ok(simulated),
+                _ => Err(format!("--{}-min and --{}-max go together", name, name)),
+            };
+            let temps = grid("t", args.t_min, args.t_max, args.t_step, temps)?;
+            let fields = grid("h", args.h_min, args.h_max, args.h_step, fields)?;
+
+            print!("{} ", simulation_type);
+
+            let wham = match args.wham {
+                true => {
+                    let wham = reweight::Wham::new(&series);
+                    eprintln!(
+                        "[wham]: {} histograms solved in {} iterations",
+                        series.histograms.len(),
+                        wham.iterations
+                    );
+
+                    Some(wham)
+                }
+                false => None,
+            };
+
+            if let Some(dir) = Path::new(&args.out).parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            let out_path = format!("{}.csv", args.out);
+            let mut writer = csv::Writer::from_path(&out_path)?;
+            writer.write_record(["T", "H", "M", "absM", "E", "X", "C", "U4", "ess"])?;
+
+            for &temp in temps.iter() {
+                for &h in fields.iter() {
+                    let estimate = match wham.as_ref() {
+                        Some(wham) => wham.estimate(temp, h),
+                        None => series.single(temp, h),
+                    };
+
+                    if let Some(e) = estimate {
+                        writer.serialize((e.temp, e.h, e.m, e.m_abs, e.e, e.x, e.c, e.u4, e.ess))?;
+                    }
+                }
+            }
+
+            writer.flush()?;
+            print!("{} ", out_path);
+
+            return Ok(());
+        }
+        Some(simulation_type) if simulation_type.as_str() == "aggregate" => {
+            let args = cli::ArgsAggregate::parse_from(env::args().skip(1));
+
+            print!("{} ", simulation_type);
+            print!("{} ", errors::aggregate(&args.paths, args.resamples, &args.out)?);
+
+            return Ok(());
+        }
+        Some(simulation_type) if simulation_type.as_str() == "dos" => {
+            let args = cli::ArgsDos::parse_from(env::args().skip(1));
+            let config = ThermoConfig {
+                t_min: args.t_min,
+                t_max: args.t_max,
+                t_step: args.t_step,
+                kb: 1f64,
+            };
+
+            print!("{} ", simulation_type);
+
+            for path_str in args.paths.iter() {
+                let path = Path::new(path_str);
+                let thermo_path = path.with_file_name("thermo.csv");
+
+                DensityOfStates::load(path)?.save_thermodynamics(&thermo_path, &config)?;
+                print!("{} ", thermo_path.display());
+            }
+
+            return Ok(());
+        }
         
         x => {
             eprintln!("unknown simulation type {:?}", x);
@@ -307,6 +1396,16 @@ fn main() -> Result<(), Box<dyn Error>> {
                 print!("{} ", child.msg);
             }
 
+            let loops = match loops_of {
+                Some((args, root, make_data_path)) => save_loops(&args, &root, make_data_path)?,
+                None => vec![],
+            };
+
+            // stdout lists only the descriptors the plotting scripts read
+            for path in loops {
+                eprintln!("loop metrics: {}", path);
+            }
+
             Ok(())
         }
     }
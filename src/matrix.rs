@@ -2,7 +2,7 @@ use std::ops::{Index, IndexMut};
 
 use serde::Serialize;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Matrix<T> {
     width: usize,
     height: usize,
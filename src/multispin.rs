@@ -0,0 +1,255 @@
+use std::error::Error;
+
+use rand::RngCore;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    network::NetworkType,
+    simulation::{Engine, Simulation},
+};
+
+/// Bits of precision kept for the acceptance probabilities.
+const PROB_BITS: u32 = 32;
+const WORD_BITS: usize = 64;
+
+/// Multi-spin coded Metropolis engine driving a `Simulation` on the regular
+/// lattice. Every row is cut into `lanes` stretches of `stride` sites, and
+/// bit `k` of word `w` of the row holds the site x = k·stride + w (1 = spin
+/// up): with a stride of at least 2 no two sites of a word are neighbours,
+/// so a whole word is updated at once.
+///
+/// The spins are read in on the first sweep and from then on live in the
+/// words, which are written back into the `Simulation` only by `sync`.
+#[derive(Debug)]
+pub struct MultiSpin {
+    size: usize,
+    lanes: usize,
+    stride: usize,
+    /// the `lanes` bits of a word in use
+    mask: u64,
+    /// `stride` words per row, row after row
+    words: Vec<u64>,
+    /// whether `words` hold the spins yet
+    packed: bool,
+    /// whether the spins of the `Simulation` are those of `words`
+    synced: bool,
+    /// acceptance threshold for [antiparallel neighbours][spin is up],
+    /// as a fixed point fraction of 2^PROB_BITS
+    acceptance: [[u64; 2]; 5],
+    /// (T, H) `acceptance` was built for
+    built_for: Option<(f64, f64)>,
+}
+
+impl MultiSpin {
+    /// Errs off the regular lattice, whose four neighbours the words assume.
+    pub fn new(s: &Simulation) -> Result<Self, Box<dyn Error>> {
+        if let NetworkType::Irregular = s.config.network_type {
+            return Err("multi-spin coding needs the regular lattice".into());
+        }
+
+        let size = s.network.size;
+        let lanes = (1..=WORD_BITS.min(size / 2))
+            .rev()
+            .find(|&lanes| size.is_multiple_of(lanes))
+            .unwrap_or(1);
+        let stride = size / lanes;
+
+        Ok(MultiSpin {
+            size,
+            lanes,
+            stride,
+            mask: if lanes == WORD_BITS { u64::MAX } else { (1 << lanes) - 1 },
+            words: vec![0; size * stride],
+            packed: false,
+            synced: true,
+            acceptance: [[0; 2]; 5],
+            built_for: None,
+        })
+    }
+
+    /// Word `w` of row `y`, shifted by one site to the left (`dx` = -1) or
+    /// right (`dx` = 1) and wrapping around the row.
+    fn shifted(&self, y: usize, w: usize, dx: i64) -> u64 {
+        let row = &self.words[y * self.stride..(y + 1) * self.stride];
+
+        match dx {
+            -1 if w > 0 => row[w - 1],
+            1 if w + 1 < self.stride => row[w + 1],
+            // x - 1 of the first word is the last word one lane down
+            -1 => self.rotate(row[self.stride - 1], 1),
+            // x + 1 of the last word is the first word one lane up
+            _ => self.rotate(row[0], self.lanes - 1),
+        }
+    }
+
+    /// Rotates the lanes of `v` up by `by`.
+    fn rotate(&self, v: u64, by: usize) -> u64 {
+        match self.lanes {
+            WORD_BITS => v.rotate_left(by as u32),
+            1 => v,
+            lanes => ((v << by) | (v >> (lanes - by))) & self.mask,
+        }
+    }
+
+    fn neighbours(&self, y: usize, w: usize) -> [u64; 4] {
+        let l = self.size;
+
+        [
+            self.shifted(y, w, -1),
+            self.shifted(y, w, 1),
+            self.words[((y + l - 1) % l) * self.stride + w],
+            self.words[((y + 1) % l) * self.stride + w],
+        ]
+    }
+
+    fn rebuild_acceptance(&mut self, s: &Simulation) {
+        let kt = s.config.kb * s.config.temp;
+
+        for anti in 0..5 {
+            for up in 0..2 {
+                let spin = if up == 1 { 1. } else { -1. };
+                let d_ham = 2. * s.config.j * (4. - 2. * anti as f64) + 2. * s.config.h * spin;
+                let p = if d_ham <= 0. { 1. } else { (-d_ham / kt).exp() };
+
+                self.acceptance[anti][up] = (p * (1u64 << PROB_BITS) as f64).round() as u64;
+            }
+        }
+
+        self.built_for = Some((s.config.temp, s.config.h));
+    }
+
+    /// Lanes of a word to flip, given the lanes of every acceptance class.
+    /// Each lane draws its own uniform number bit by bit from the top, one
+    /// random word giving the same bit of every lane, and is decided as soon
+    /// as that bit differs from the one of its threshold; so the random
+    /// words are shared by all the classes and stop once every lane is.
+    fn accepted(&self, classes: &[[u64; 2]; 5], rng: &mut ChaCha20Rng) -> u64 {
+        let (mut certain, mut open) = (0u64, 0u64);
+
+        for (class, thresholds) in classes.iter().zip(self.acceptance.iter()) {
+            for (&lanes, &threshold) in class.iter().zip(thresholds.iter()) {
+                match threshold {
+                    0 => (),
+                    t if t >= 1 << PROB_BITS => certain |= lanes,
+                    _ => open |= lanes,
+                }
+            }
+        }
+
+        let mut below = 0u64;
+        for bit in (0..PROB_BITS).rev() {
+            if open == 0 {
+                break;
+            }
+
+            let threshold_bits = classes
+                .iter()
+                .zip(self.acceptance.iter())
+                .flat_map(|(class, thresholds)| class.iter().zip(thresholds.iter()))
+                .filter(|(_, &t)| (t >> bit) & 1 == 1)
+                .fold(0u64, |bits, (&lanes, _)| bits | lanes);
+            let random = rng.next_u64();
+
+            below |= open & threshold_bits & !random;
+            open &= !(threshold_bits ^ random);
+        }
+
+        certain | below
+    }
+
+    fn evolve_word(&mut self, y: usize, w: usize, rng: &mut ChaCha20Rng) {
+        let i = y * self.stride + w;
+        let s = self.words[i];
+        let [a, b, c, d] = self.neighbours(y, w).map(|n| n ^ s);
+
+        // bit-sliced count of antiparallel neighbours, (c2 c1 c0) in binary
+        let (x1, k1) = (a ^ b, a & b);
+        let (x2, k2) = (c ^ d, c & d);
+        let c0 = x1 ^ x2;
+        let k0 = x1 & x2;
+        let c1 = k1 ^ k2 ^ k0;
+        let c2 = (k1 & k2) | ((k1 ^ k2) & k0);
+
+        let counts = [
+            !c2 & !c1 & !c0,
+            !c2 & !c1 & c0,
+            !c2 & c1 & !c0,
+            !c2 & c1 & c0,
+            c2,
+        ];
+        let classes = counts.map(|lanes| [lanes & !s & self.mask, lanes & s & self.mask]);
+
+        self.words[i] = s ^ self.accepted(&classes, rng);
+    }
+
+    fn pack(&mut self, s: &Simulation) {
+        for y in 0..self.size {
+            for w in 0..self.stride {
+                self.words[y * self.stride + w] = (0..self.lanes)
+                    .filter(|&k| s.network.spins[(k * self.stride + w, y)] > 0)
+                    .fold(0, |word, k| word | 1 << k);
+            }
+        }
+    }
+
+    /// Sets the spin and bond sums of `s` counted off the words.
+    fn count(&self, s: &mut Simulation) {
+        let (mut up, mut anti) = (0, 0);
+
+        for y in 0..self.size {
+            for w in 0..self.stride {
+                let word = self.words[y * self.stride + w];
+                let [_, right, _, down] = self.neighbours(y, w);
+
+                up += word.count_ones() as i64;
+                anti += ((word ^ right) & self.mask).count_ones() as i64 + ((word ^ down) & self.mask).count_ones() as i64;
+            }
+        }
+
+        let n = (self.size * self.size) as i64;
+        s.spin_sum = 2 * up - n;
+        s.bond_sum = 2 * n - 2 * anti;
+    }
+}
+
+impl Engine for MultiSpin {
+    /// One sweep of every site, a word at a time in row order, at the T and
+    /// H `s` is at.
+    fn sweep(&mut self, s: &mut Simulation, rng: &mut ChaCha20Rng) {
+        if !self.packed {
+            self.pack(s);
+            self.packed = true;
+        }
+        if self.built_for != Some((s.config.temp, s.config.h)) {
+            self.rebuild_acceptance(s);
+        }
+
+        for y in 0..self.size {
+            for w in 0..self.stride {
+                self.evolve_word(y, w, rng);
+            }
+        }
+
+        self.count(s);
+        self.synced = false;
+    }
+
+    /// Writes the words back into the spins of `s`.
+    fn sync(&mut self, s: &mut Simulation) {
+        if self.synced {
+            return;
+        }
+
+        for y in 0..self.size {
+            for w in 0..self.stride {
+                let word = self.words[y * self.stride + w];
+
+                for k in 0..self.lanes {
+                    s.network.spins[(k * self.stride + w, y)] = if (word >> k) & 1 == 1 { 1 } else { -1 };
+                }
+            }
+        }
+
+        self.synced = true;
+    }
+}
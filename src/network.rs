@@ -4,7 +4,7 @@ use crate::matrix::{index_of_pos, Matrix};
 use plotters::prelude::*;
 use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[macro_export]
 macro_rules! frame {
@@ -16,7 +16,7 @@ macro_rules! frame {
     };
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum NetworkType {
     Regular,
     Irregular,
@@ -49,7 +49,7 @@ impl ToString for NetworkType {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Network {
     pub size: usize,
     pub spins: Matrix<i8>,
@@ -72,6 +72,10 @@ impl Network {
         m
     }
 
+    pub fn randomise_spins(&mut self, rand: &mut ChaCha20Rng) {
+        self.spins = Network::make_spins(self.size, rand);
+    }
+
     fn validate_network(&self) {
         for (p, ns) in self.lattice.enumerator() {
             let i = index_of_pos(self.size, p);
@@ -222,6 +226,37 @@ impl Network {
             .collect()
     }
 
+    pub fn local_field(&self, (x, y): (usize, usize)) -> i64 {
+        self.lattice[(x, y)]
+            .iter()
+            .map(|&i| self.spins[i] as i64)
+            .sum()
+    }
+
+    /// Greedy colouring in index order; on the even sized regular lattice
+    /// this is the checkerboard. Returns the sites of each colour class.
+    pub fn colouring(&self) -> Vec<Vec<usize>> {
+        let mut colour_of: Vec<Option<usize>> = vec![None; self.size * self.size];
+        let mut classes: Vec<Vec<usize>> = vec![];
+
+        for i in 0..colour_of.len() {
+            let taken: Vec<usize> = self.lattice[i]
+                .iter()
+                .filter_map(|&j| colour_of[j])
+                .collect();
+            let colour = (0..).find(|c| !taken.contains(c)).unwrap();
+
+            if colour == classes.len() {
+                classes.push(vec![]);
+            }
+
+            classes[colour].push(i);
+            colour_of[i] = Some(colour);
+        }
+
+        classes
+    }
+
     pub fn get_spin(&self, (x, y): (usize, usize)) -> i8 {
         self.spins[(x, y)]
     }
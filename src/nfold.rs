@@ -0,0 +1,221 @@
+use std::{error::Error, path::Path};
+
+use csv::Writer;
+use rand::Rng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    matrix::pos_of_index,
+    equilibrium::{equilibrate_with, MAX_SWEEPS},
+    simulation::{grid_point, save_equilibration, HysteresisConfig, MeasureConfig, Simulation, HYSTERESIS_COLUMNS, SERIES_COLUMNS},
+};
+
+/// Rejection-free (n-fold way, Bortz–Kalos–Lebowitz) engine driving a
+/// `Simulation`. Spins are grouped by their (local field, spin) flip class,
+/// every event flips one spin, and time advances by the number of
+/// random-site Metropolis attempts the flip would have taken on average,
+/// expressed in sweeps of N attempts.
+#[derive(Debug)]
+pub struct NFold {
+    members: Vec<Vec<usize>>,
+    slot: Vec<usize>,
+    class_of: Vec<usize>,
+    /// elapsed time in MC sweeps
+    pub time: f64,
+}
+
+impl NFold {
+    pub fn new(s: &Simulation) -> Self {
+        let n = s.network.size * s.network.size;
+        let mut nfold = NFold {
+            members: vec![vec![]; s.flip_classes()],
+            slot: vec![0; n],
+            class_of: vec![0; n],
+            time: 0.,
+        };
+
+        for i in 0..n {
+            let class = s.flip_class(pos_of_index(s.network.size, i));
+
+            nfold.class_of[i] = class;
+            nfold.slot[i] = nfold.members[class].len();
+            nfold.members[class].push(i);
+        }
+
+        nfold
+    }
+
+    fn reclassify(&mut self, s: &Simulation, i: usize) {
+        let class = s.flip_class(pos_of_index(s.network.size, i));
+        let old = self.class_of[i];
+
+        if class == old {
+            return;
+        }
+
+        let slot = self.slot[i];
+        self.members[old].swap_remove(slot);
+        if let Some(&moved) = self.members[old].get(slot) {
+            self.slot[moved] = slot;
+        }
+
+        self.class_of[i] = class;
+        self.slot[i] = self.members[class].len();
+        self.members[class].push(i);
+    }
+
+    fn total_rate(&self, s: &Simulation) -> f64 {
+        self.members
+            .iter()
+            .enumerate()
+            .map(|(c, ms)| ms.len() as f64 * s.flip_rate(c))
+            .sum()
+    }
+
+    /// Time in sweeps until the next flip: the number of attempts is
+    /// geometric with success probability R / N.
+    fn waiting_time(&self, s: &Simulation, rate: f64, rng: &mut ChaCha20Rng) -> f64 {
+        let n = s.network.size2;
+        let q = rate / n;
+
+        if q <= 0. {
+            return f64::INFINITY;
+        }
+
+        let attempts = if q >= 1. {
+            1.
+        } else {
+            1. + (rng.gen::<f64>().ln() / (1. - q).ln()).floor()
+        };
+
+        attempts / n
+    }
+
+    fn flip_event(&mut self, s: &mut Simulation, rate: f64, rng: &mut ChaCha20Rng) {
+        let mut target = rng.gen::<f64>() * rate;
+        let mut class = 0;
+
+        for (c, ms) in self.members.iter().enumerate() {
+            let weight = ms.len() as f64 * s.flip_rate(c);
+
+            if weight > 0. {
+                class = c;
+
+                if target < weight {
+                    break;
+                }
+                target -= weight;
+            }
+        }
+
+        let members = &self.members[class];
+        let i = members[rng.gen_range(0..members.len())];
+
+        s.flip(pos_of_index(s.network.size, i));
+
+        self.reclassify(s, i);
+        for &j in s.network.lattice[i].iter() {
+            self.reclassify(s, j);
+        }
+    }
+
+    /// Runs until one more sweep has passed; waiting times are memoryless,
+    /// so an event that would cross the end of the sweep is simply redrawn.
+    fn sweep(&mut self, s: &mut Simulation, rng: &mut ChaCha20Rng) {
+        let end = self.time + 1.;
+
+        loop {
+            let rate = self.total_rate(s);
+            let dt = self.waiting_time(s, rate, rng);
+
+            if self.time + dt >= end {
+                self.time = end;
+                break;
+            }
+
+            self.time += dt;
+            self.flip_event(s, rate, rng);
+        }
+    }
+
+    /// Samples the state every `config.interval` sweeps, like `Simulation::measure`.
+    pub fn measure(&mut self, s: &mut Simulation, config: &MeasureConfig, rng: &mut ChaCha20Rng) {
+        s.measure_with(config, rng, &mut |s: &mut Simulation, rng: &mut ChaCha20Rng| self.sweep(s, rng));
+    }
+
+    pub fn simulate_hysteresis(
+        &mut self,
+        s: &mut Simulation,
+        data_dist_path: &Path,
+        config: HysteresisConfig,
+        rand: &mut ChaCha20Rng,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut data_writer = Writer::from_path(data_dist_path)?;
+        // Write header
+        data_writer.write_record(HYSTERESIS_COLUMNS)?;
+        data_writer.flush()?;
+
+        let mut series_writer = match config.measure.series {
+            true => {
+                let mut writer = Writer::from_path(format!("{}/series.csv", s.dist))?;
+                writer.write_record(SERIES_COLUMNS)?;
+
+                Some(writer)
+            }
+            false => None,
+        };
+
+        let mut equilibrium_writer = Writer::from_path(format!("{}/equilibrium.csv", s.dist))?;
+        equilibrium_writer.write_record(["H", "criterion", "fired", "sweeps"])?;
+
+        s.refresh_measurements();
+
+        let h_index_max = (config.h_max / config.h_step).round() as i64;
+        let h_index_min = (config.h_min / config.h_step).round() as i64;
+        let mut h_index = (s.config.h / config.h_step).round() as i64;
+
+        let mut saw_max = false;
+        let mut step_direction = 1i64;
+
+        self.time = 0.;
+        equilibrate_with(s, MAX_SWEEPS, rand, |s, rng| self.sweep(s, rng), |_, _| Ok(()))?;
+        self.time = 0.;
+
+        while !(h_index >= h_index_max && saw_max) {
+            let is_max = h_index >= h_index_max || h_index <= h_index_min;
+
+            if is_max {
+                step_direction *= -1;
+            }
+
+            saw_max |= is_max;
+
+            // simulate
+            s.n = 0;
+            let equilibration =
+                equilibrate_with(s, MAX_SWEEPS, rand, |s, rng| self.sweep(s, rng), |_, _| Ok(()))?;
+            save_equilibration(&mut equilibrium_writer, s.config.h, equilibration)?;
+            self.measure(s, &config.measure, rand);
+
+            // update measurements
+            s.refresh_measurements();
+            s.time = self.time.round() as u128;
+
+            // save
+            let mut row = s.snapshot_hysteresis()?;
+            row[0] = self.time;
+            data_writer.serialize(row)?;
+            if let Some(writer) = series_writer.as_mut() {
+                s.save_series(writer, &config.measure)?;
+            }
+
+            // step
+            h_index += step_direction;
+            s.set_field(grid_point(0., h_index, config.h_step));
+        }
+
+        data_writer.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    error::Error,
+    f64::consts::{FRAC_PI_2, PI},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use csv::{Reader, Writer};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::descriptor;
+
+/// Largest strip width the transfer matrix is built for, 2^MAX_STRIP_WIDTH states per row.
+pub const MAX_STRIP_WIDTH: usize = 16;
+
+/// Critical temperature of the infinite square lattice, for J = kB = 1.
+pub fn onsager_tc() -> f64 {
+    2. / (1. + 2f64.sqrt()).ln()
+}
+
+/// Complete elliptic integral of the first kind K(k), through the
+/// arithmetic-geometric mean.
+fn elliptic_k(k: f64) -> f64 {
+    let (mut a, mut b) = (1f64, (1. - k * k).sqrt());
+
+    while (a - b).abs() > 1e-15 {
+        let a_next = (a + b) / 2.;
+        b = (a * b).sqrt();
+        a = a_next;
+    }
+
+    FRAC_PI_2 / a
+}
+
+/// Onsager–Yang spontaneous magnetisation per spin.
+pub fn onsager_mag(temp: f64) -> f64 {
+    if temp >= onsager_tc() {
+        return 0.;
+    }
+
+    (1. - (2. / temp).sinh().powi(-4)).powf(1. / 8.)
+}
+
+/// Onsager energy per spin.
+pub fn onsager_energy(temp: f64) -> f64 {
+    let b = 1. / temp;
+    let th = (2. * b).tanh();
+    let k = 2. * (2. * b).sinh() / (2. * b).cosh().powi(2);
+    let k_int = elliptic_k(k.min(1. - 1e-16));
+
+    -1. / th * (1. + 2. / PI * (2. * th * th - 1.) * k_int)
+}
+
+/// Onsager specific heat per spin, dE/dT of the closed form energy.
+pub fn onsager_heat(temp: f64) -> f64 {
+    let dt = 1e-5 * temp;
+
+    (onsager_energy(temp + dt) - onsager_energy(temp - dt)) / (2. * dt)
+}
+
+/// Row-to-row transfer matrix of a periodic strip of width `width`, applied
+/// in factorised form T = D^½ V D^½: D holds the in-row bonds and the field,
+/// V the bonds between rows, one 2×2 factor per column.
+#[derive(Debug)]
+pub struct TransferMatrix {
+    pub width: usize,
+    pub j: f64,
+}
+
+impl TransferMatrix {
+    /// Errs when the strip is empty or too wide to build the matrix for.
+    pub fn new(width: usize, j: f64) -> Result<Self, Box<dyn Error>> {
+        if width == 0 || width > MAX_STRIP_WIDTH {
+            return Err(format!(
+                "strip width for the transfer matrix out of range: {} (1 to {})",
+                width, MAX_STRIP_WIDTH
+            )
+            .into());
+        }
+
+        Ok(TransferMatrix { width, j })
+    }
+
+    fn row_weights(&self, beta: f64, h: f64) -> Vec<f64> {
+        let w = self.width;
+
+        (0..1usize << w)
+            .map(|row| {
+                let spin = |i: usize| if (row >> (i % w)) & 1 == 1 { 1. } else { -1. };
+                let bonds: f64 = (0..w).map(|i| spin(i) * spin(i + 1)).sum();
+                let field: f64 = (0..w).map(spin).sum();
+
+                (beta * (self.j * bonds + h * field) / 2.).exp()
+            })
+            .collect()
+    }
+
+    fn apply(&self, half: &[f64], same: f64, flipped: f64, v: &mut [f64]) {
+        for (x, d) in v.iter_mut().zip(half.iter()) {
+            *x *= d;
+        }
+
+        for i in 0..self.width {
+            let bit = 1usize << i;
+
+            for row in 0..v.len() {
+                if row & bit == 0 {
+                    let (a, b) = (v[row], v[row | bit]);
+
+                    v[row] = same * a + flipped * b;
+                    v[row | bit] = flipped * a + same * b;
+                }
+            }
+        }
+
+        for (x, d) in v.iter_mut().zip(half.iter()) {
+            *x *= d;
+        }
+    }
+
+    /// ln λ_max, by power iteration from the uniform (flip symmetric) vector.
+    pub fn ln_lambda(&self, temp: f64, h: f64) -> f64 {
+        let beta = 1. / temp;
+        let half = self.row_weights(beta, h);
+        let (same, flipped) = ((beta * self.j).exp(), (-beta * self.j).exp());
+
+        let mut v = vec![1.; 1 << self.width];
+        let mut ln_lambda = 0.;
+
+        for _ in 0..100_000 {
+            let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+            v.iter_mut().for_each(|x| *x /= norm);
+
+            let prev: Vec<f64> = v.clone();
+            self.apply(&half, same, flipped, &mut v);
+
+            let next = v.iter().zip(prev.iter()).map(|(a, b)| a * b).sum::<f64>().ln();
+            let converged = (next - ln_lambda).abs() < 1e-14;
+            ln_lambda = next;
+
+            if converged {
+                break;
+            }
+        }
+
+        ln_lambda
+    }
+
+    /// Free energy per spin.
+    pub fn free_energy(&self, temp: f64, h: f64) -> f64 {
+        -temp * self.ln_lambda(temp, h) / self.width as f64
+    }
+
+    /// Magnetisation per spin, -∂f/∂H.
+    pub fn mag(&self, temp: f64, h: f64) -> f64 {
+        let dh = 1e-5;
+
+        -(self.free_energy(temp, h + dh) - self.free_energy(temp, h - dh)) / (2. * dh)
+    }
+
+    /// Energy per spin, -∂ ln λ / ∂β per column.
+    pub fn energy(&self, temp: f64, h: f64) -> f64 {
+        let beta = 1. / temp;
+        let db = 1e-5 * beta;
+        let ln_l = |b: f64| self.ln_lambda(1. / b, h);
+
+        -(ln_l(beta + db) - ln_l(beta - db)) / (2. * db) / self.width as f64
+    }
+
+    /// Specific heat per spin, β² ∂² ln λ / ∂β² per column.
+    pub fn heat(&self, temp: f64, h: f64) -> f64 {
+        let beta = 1. / temp;
+        let db = 1e-3 * beta;
+        let ln_l = |b: f64| self.ln_lambda(1. / b, h);
+
+        beta * beta * (ln_l(beta + db) - 2. * ln_l(beta) + ln_l(beta - db))
+            / (db * db)
+            / self.width as f64
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Reference {
+    Onsager,
+    Strip(usize),
+}
+
+impl Reference {
+    /// (M, E, C) per spin at (T, H); the Onsager solution only exists at H = 0.
+    pub fn at(&self, temp: f64, h: f64) -> Result<(f64, f64, f64), Box<dyn Error>> {
+        Ok(match self {
+            Reference::Onsager if h == 0. => (
+                onsager_mag(temp),
+                onsager_energy(temp),
+                onsager_heat(temp),
+            ),
+            Reference::Onsager => (f64::NAN, f64::NAN, f64::NAN),
+            &Reference::Strip(width) => {
+                let tm = TransferMatrix::new(width, 1.)?;
+
+                (tm.mag(temp, h), tm.energy(temp, h), tm.heat(temp, h))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Deviation {
+    pub points: usize,
+    pub mag_points: usize,
+    pub rms_mag: f64,
+    pub max_mag: f64,
+    pub rms_energy: f64,
+    pub max_energy: f64,
+    pub heat_points: usize,
+    pub rms_heat: f64,
+}
+
+/// Reads a phase or hysteresis run from its `desc.json`, writes the run
+/// next to the reference curve into `compare.csv` and the deviations into
+/// `compare.json`, both beside the descriptor. Runs are read by their
+/// averages over every sweep, aM and aE, where they have them. In a field M
+/// is compared with its sign, so that a metastable branch shows; at H = 0,
+/// where a finite lattice tunnelling between the ordered states averages its
+/// signed M to 0, ⟨|M|⟩ is compared with the Onsager magnetisation, and M not
+/// at all against a strip, which has none.
+pub fn compare(desc_path: &Path, reference: Reference) -> Result<(PathBuf, Deviation), Box<dyn Error>> {
+    let desc: Value = serde_json::from_str(&fs::read_to_string(desc_path)?)?;
+    let size = desc["config"]["size"].as_f64().unwrap_or(1.);
+    let spins = size * size;
+    let temp = desc["temp"].as_f64();
+    let data_path = descriptor::data_path_of(&desc, desc_path);
+
+    let mut reader = Reader::from_path(&data_path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (col_t, col_h) = (column("T"), column("H"));
+    let (col_m, col_abs_m) = (column("aM").or(column("M")), column("absM"));
+    let (col_e, col_c) = (column("aE").or(column("E")), column("C"));
+
+    let out_path = desc_path.with_file_name("compare.csv");
+    let mut writer = Writer::from_path(&out_path)?;
+    writer.write_record(["T", "H", "M", "M_ref", "E", "E_ref", "C", "C_ref"])?;
+
+    let mut dev = Deviation::default();
+    let (mut sq_mag, mut sq_energy, mut sq_heat) = (0., 0., 0.);
+
+    for record in reader.records() {
+        let record = record?;
+        let get = |c: Option<usize>| c.and_then(|c| record[c].parse::<f64>().ok());
+
+        let (t, h) = match (get(col_t), get(col_h), temp) {
+            (Some(t), h, _) => (t, h.unwrap_or(0.)),
+            (None, Some(h), Some(t)) => (t, h),
+            _ => continue,
+        };
+        let e = get(col_e).unwrap_or(f64::NAN) / spins;
+        let c = get(col_c).unwrap_or(f64::NAN);
+        let (m_ref, e_ref, c_ref) = reference.at(t, h)?;
+        let (m, m_ref) = match reference {
+            _ if h != 0. => (get(col_m).unwrap_or(f64::NAN), m_ref),
+            Reference::Onsager => (get(col_abs_m).or(get(col_m)).unwrap_or(f64::NAN).abs(), m_ref),
+            Reference::Strip(_) => (f64::NAN, f64::NAN),
+        };
+
+        writer.serialize((t, h, m, m_ref, e, e_ref, c, c_ref))?;
+
+        if (c - c_ref).is_finite() {
+            dev.heat_points += 1;
+            sq_heat += (c - c_ref).powi(2);
+        }
+
+        if (m - m_ref).is_finite() {
+            dev.mag_points += 1;
+            sq_mag += (m - m_ref).powi(2);
+            dev.max_mag = dev.max_mag.max((m - m_ref).abs());
+        }
+
+        if (e - e_ref).is_finite() {
+            dev.points += 1;
+            sq_energy += (e - e_ref).powi(2);
+            dev.max_energy = dev.max_energy.max((e - e_ref).abs());
+        }
+    }
+
+    writer.flush()?;
+
+    dev.rms_mag = (sq_mag / dev.mag_points.max(1) as f64).sqrt();
+    dev.rms_energy = (sq_energy / dev.points.max(1) as f64).sqrt();
+    dev.rms_heat = (sq_heat / dev.heat_points.max(1) as f64).sqrt();
+
+    fs::write(
+        desc_path.with_file_name("compare.json"),
+        serde_json::to_string(&dev)?,
+    )?;
+
+    Ok((out_path, dev))
+}
@@ -0,0 +1,266 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+use csv::Reader;
+use serde_json::Value;
+
+const TOLERANCE: f64 = 1e-8;
+const MAX_ITERATIONS: usize = 100_000;
+
+/// Samples of one simulated point: β, H and the (E_J, M) of every sampled
+/// sweep, E_J being the exchange part of the energy, -J Σ s_i s_j, so that
+/// the energy at any field is E_J - H N M.
+#[derive(Debug)]
+pub struct Histogram {
+    pub temp: f64,
+    pub h: f64,
+    pub samples: Vec<(f64, f64)>,
+}
+
+/// The histograms of every run read back from `series.csv`.
+#[derive(Debug)]
+pub struct Series {
+    pub spins: f64,
+    pub kb: f64,
+    pub histograms: Vec<Histogram>,
+}
+
+/// Observables at one (T, H), from weighted samples.
+#[derive(Debug, Clone, Copy)]
+pub struct Estimate {
+    pub temp: f64,
+    pub h: f64,
+    pub m: f64,
+    pub m_abs: f64,
+    /// energy per spin
+    pub e: f64,
+    pub x: f64,
+    pub c: f64,
+    pub u4: f64,
+    /// Kish effective sample size of the weights
+    pub ess: f64,
+}
+
+fn log_sum_exp(xs: impl Iterator<Item = f64> + Clone) -> f64 {
+    let max = xs.clone().fold(f64::NEG_INFINITY, f64::max);
+
+    if max.is_infinite() {
+        return max;
+    }
+
+    max + xs.map(|x| (x - max).exp()).sum::<f64>().ln()
+}
+
+impl Series {
+    /// Reads the `series.csv` beside every descriptor. Runs of different
+    /// seeds at the same T and H are pooled into one histogram. Only phase
+    /// runs are taken: the two branches of a hysteresis loop at the same H
+    /// are different metastable states, not samples of one ensemble.
+    pub fn load(desc_paths: &[String], kb: f64) -> Result<Self, Box<dyn Error>> {
+        let mut spins = None;
+        let mut points: BTreeMap<(i64, i64), Histogram> = BTreeMap::new();
+
+        for path in desc_paths {
+            let desc_path = Path::new(path);
+            let desc: Value = serde_json::from_str(&fs::read_to_string(desc_path)?)?;
+
+            if desc["config"].get("t_min").is_none() {
+                return Err(format!("{}: not a phase run, hysteresis branches can't be reweighted", path).into());
+            }
+
+            let size = desc["config"]["size"].as_f64().unwrap_or(0.);
+            let n = size * size;
+
+            if spins.is_some_and(|s| s != n) {
+                return Err(format!("{}: size differs from the other runs", path).into());
+            }
+            spins = Some(n);
+
+            let series_path = desc_path.with_file_name("series.csv");
+            if !series_path.exists() {
+                return Err(format!("{}: no series.csv beside it, was the run made with --no-series?", path).into());
+            }
+            let mut reader = Reader::from_path(&series_path)?;
+            let headers = reader.headers()?.clone();
+            let column = |name: &str| {
+                headers
+                    .iter()
+                    .position(|h| h == name)
+                    .ok_or(format!("{}: no {} column", series_path.display(), name))
+            };
+            let (col_t, col_h, col_e, col_m) = (column("T")?, column("H")?, column("E")?, column("M")?);
+
+            for record in reader.records() {
+                let record = record?;
+                let get = |c: usize| record[c].trim().parse::<f64>();
+                let (temp, h, e, m) = (get(col_t)?, get(col_h)?, get(col_e)?, get(col_m)?);
+
+                points
+                    .entry(((temp * 1e9).round() as i64, (h * 1e9).round() as i64))
+                    .or_insert(Histogram { temp, h, samples: vec![] })
+                    .samples
+                    .push((e + h * n * m, m));
+            }
+        }
+
+        Ok(Series {
+            spins: spins.unwrap_or(0.),
+            kb,
+            histograms: points.into_values().collect(),
+        })
+    }
+
+    /// The distinct T and H the runs were simulated at, in order.
+    pub fn points(&self) -> (Vec<f64>, Vec<f64>) {
+        let distinct = |f: fn(&Histogram) -> f64| {
+            let mut xs: Vec<f64> = self.histograms.iter().map(f).collect();
+            xs.sort_by(f64::total_cmp);
+            xs.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+
+            xs
+        };
+
+        (distinct(|k| k.temp), distinct(|k| k.h))
+    }
+
+    fn beta(&self, temp: f64) -> f64 {
+        1. / (self.kb * temp)
+    }
+
+    /// β (E_J - H N M) of a sample at (T, H).
+    fn reduced(&self, (e_j, m): (f64, f64), temp: f64, h: f64) -> f64 {
+        self.beta(temp) * (e_j - h * self.spins * m)
+    }
+
+    fn estimate(&self, temp: f64, h: f64, samples: &[(f64, f64)], log_weights: &[f64]) -> Estimate {
+        let norm = log_sum_exp(log_weights.iter().copied());
+        let weights: Vec<f64> = log_weights.iter().map(|w| (w - norm).exp()).collect();
+        let mean = |f: &dyn Fn(f64, f64) -> f64| {
+            samples
+                .iter()
+                .zip(weights.iter())
+                .map(|(&(e_j, m), w)| w * f(e_j - h * self.spins * m, m))
+                .sum::<f64>()
+        };
+
+        let (m, m_abs) = (mean(&|_, m| m), mean(&|_, m| m.abs()));
+        let (m2, m4) = (mean(&|_, m| m * m), mean(&|_, m| m.powi(4)));
+        let (e, e2) = (mean(&|e, _| e), mean(&|e, _| e * e));
+
+        Estimate {
+            temp,
+            h,
+            m,
+            m_abs,
+            e: e / self.spins,
+            x: self.spins * (m2 - m_abs * m_abs) / (self.kb * temp),
+            c: (e2 - e * e) / (self.kb * temp * temp * self.spins),
+            u4: 1. - m4 / (3. * m2 * m2),
+            ess: 1. / weights.iter().map(|w| w * w).sum::<f64>(),
+        }
+    }
+
+    /// Single histogram (Ferrenberg–Swendsen) reweighting of the histogram
+    /// closest to (T, H) in β and βH.
+    pub fn single(&self, temp: f64, h: f64) -> Option<Estimate> {
+        let (beta, beta_h) = (self.beta(temp), self.beta(temp) * h);
+        let source = self.histograms.iter().filter(|k| !k.samples.is_empty()).min_by(|a, b| {
+            let d = |k: &Histogram| {
+                let b = self.beta(k.temp);
+
+                (b - beta).powi(2) + (b * k.h - beta_h).powi(2)
+            };
+
+            d(a).total_cmp(&d(b))
+        })?;
+
+        let log_weights: Vec<f64> = source
+            .samples
+            .iter()
+            .map(|&s| self.reduced(s, source.temp, source.h) - self.reduced(s, temp, h))
+            .collect();
+
+        Some(self.estimate(temp, h, &source.samples, &log_weights))
+    }
+}
+
+/// Multiple histogram reweighting: the free energies ln Z_k of every
+/// histogram solved self consistently, WHAM style, after which every sample
+/// of every run weighs in at any (T, H).
+#[derive(Debug)]
+pub struct Wham<'a> {
+    series: &'a Series,
+    samples: Vec<(f64, f64)>,
+    /// ln Σ_k n_k exp(-u_k(s) - ln Z_k) of every pooled sample
+    denominators: Vec<f64>,
+    pub iterations: usize,
+}
+
+impl<'a> Wham<'a> {
+    pub fn new(series: &'a Series) -> Self {
+        let histograms: Vec<&Histogram> = series.histograms.iter().filter(|k| !k.samples.is_empty()).collect();
+        let samples: Vec<(f64, f64)> = histograms.iter().flat_map(|k| k.samples.iter().copied()).collect();
+        let log_counts: Vec<f64> = histograms.iter().map(|k| (k.samples.len() as f64).ln()).collect();
+
+        // u[k][s], reduced energy of sample s at the point of histogram k
+        let u: Vec<Vec<f64>> = histograms
+            .iter()
+            .map(|k| samples.iter().map(|&s| series.reduced(s, k.temp, k.h)).collect())
+            .collect();
+
+        let mut log_z = vec![0.; histograms.len()];
+        let mut denominators = vec![0.; samples.len()];
+        let mut iterations = 0;
+
+        while !histograms.is_empty() && iterations < MAX_ITERATIONS {
+            iterations += 1;
+
+            for (i, d) in denominators.iter_mut().enumerate() {
+                *d = log_sum_exp((0..histograms.len()).map(|k| log_counts[k] - u[k][i] - log_z[k]));
+            }
+
+            let next: Vec<f64> = u
+                .iter()
+                .map(|u_k| log_sum_exp(u_k.iter().zip(denominators.iter()).map(|(u, d)| -u - d)))
+                .collect();
+
+            // Z is only known up to a constant
+            let shift = next[0];
+            let change = next
+                .iter()
+                .zip(log_z.iter())
+                .fold(0f64, |c, (n, z)| c.max((n - shift - z).abs()));
+
+            log_z = next.iter().map(|n| n - shift).collect();
+
+            if change < TOLERANCE {
+                break;
+            }
+        }
+
+        for (i, d) in denominators.iter_mut().enumerate() {
+            *d = log_sum_exp((0..histograms.len()).map(|k| log_counts[k] - u[k][i] - log_z[k]));
+        }
+
+        Wham {
+            series,
+            samples,
+            denominators,
+            iterations,
+        }
+    }
+
+    pub fn estimate(&self, temp: f64, h: f64) -> Option<Estimate> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let log_weights: Vec<f64> = self
+            .samples
+            .iter()
+            .zip(self.denominators.iter())
+            .map(|(&s, d)| -self.series.reduced(s, temp, h) - d)
+            .collect();
+
+        Some(self.series.estimate(temp, h, &self.samples, &log_weights))
+    }
+}
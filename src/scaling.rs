@@ -0,0 +1,295 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::Path,
+};
+
+use csv::{Reader, Writer};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::descriptor;
+
+/// Steps of the grid scan before the golden section refinement.
+const SCAN_STEPS: usize = 200;
+const GOLDEN_STEPS: usize = 60;
+
+/// One phase curve, averaged over the seeds of a network and size.
+#[derive(Debug, Default)]
+pub struct Curve {
+    pub size: u64,
+    pub seeds: usize,
+    /// T, ⟨|M|⟩, χ, U4
+    pub points: Vec<[f64; 4]>,
+}
+
+struct Run {
+    network: String,
+    size: u64,
+    points: Vec<[f64; 4]>,
+}
+
+/// Sums of the points of every seed, keyed by T in units of 1e-9 so that
+/// the grids of different seeds line up.
+#[derive(Default)]
+struct Sums {
+    seeds: usize,
+    by_t: BTreeMap<i64, ([f64; 4], f64)>,
+}
+
+/// T, ⟨|M|⟩, χ and U4 of a phase run, ⟨|M|⟩ falling back on |M| for runs
+/// that predate the column.
+fn read_run(desc_path: &Path) -> Result<Run, Box<dyn Error>> {
+    let desc: Value = serde_json::from_str(&fs::read_to_string(desc_path)?)?;
+    let data_path = descriptor::data_path_of(&desc, desc_path);
+
+    let mut reader = Reader::from_path(&data_path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let missing = |name: &str| format!("{}: no {} column", data_path.display(), name);
+    let col_t = column("T").ok_or_else(|| missing("T"))?;
+    let col_m = column("absM").or_else(|| column("M")).ok_or_else(|| missing("M"))?;
+    let col_x = column("X").ok_or_else(|| missing("X"))?;
+    let col_u = column("U4").ok_or_else(|| missing("U4"))?;
+
+    let mut points = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let get = |c: usize| record[c].trim().parse::<f64>();
+
+        points.push([get(col_t)?, get(col_m)?.abs(), get(col_x)?, get(col_u)?]);
+    }
+
+    let network = descriptor::network_of(&desc, desc_path);
+
+    Ok(Run {
+        network,
+        size: desc["config"]["size"].as_u64().unwrap_or(0),
+        points,
+    })
+}
+
+/// Reads every run and averages the seeds of each network and size point by
+/// point in T; networks come out by name, curves by size.
+pub fn load(desc_paths: &[String]) -> Result<BTreeMap<String, Vec<Curve>>, Box<dyn Error>> {
+    let mut sums: BTreeMap<(String, u64), Sums> = BTreeMap::new();
+
+    for path in desc_paths {
+        let run = read_run(Path::new(path))?;
+        let entry = sums.entry((run.network, run.size)).or_default();
+        entry.seeds += 1;
+
+        for p in run.points {
+            let (sum, count) = entry.by_t.entry((p[0] * 1e9).round() as i64).or_insert(([0.; 4], 0.));
+
+            for (s, x) in sum.iter_mut().zip(p.iter()) {
+                *s += x;
+            }
+            *count += 1.;
+        }
+    }
+
+    let mut networks: BTreeMap<String, Vec<Curve>> = BTreeMap::new();
+    for ((network, size), Sums { seeds, by_t }) in sums {
+        let points = by_t
+            .values()
+            .map(|(sum, count)| sum.map(|s| s / count))
+            .filter(|p| p.iter().all(|x| x.is_finite()))
+            .collect();
+
+        networks.entry(network).or_default().push(Curve { size, seeds, points });
+    }
+
+    Ok(networks)
+}
+
+/// Where U4 of the smaller size crosses that of the larger one. Below Tc the
+/// larger size sits higher, so the difference goes from - to +; of all such
+/// sign changes the steepest is kept, noise giving shallow ones.
+fn binder_crossing(a: &Curve, b: &Curve) -> Option<f64> {
+    let b_at: BTreeMap<i64, f64> = b.points.iter().map(|p| ((p[0] * 1e9).round() as i64, p[3])).collect();
+    let diffs: Vec<(f64, f64)> = a
+        .points
+        .iter()
+        .filter_map(|p| b_at.get(&((p[0] * 1e9).round() as i64)).map(|u| (p[0], p[3] - u)))
+        .collect();
+
+    diffs
+        .windows(2)
+        .filter(|w| w[0].1 < 0. && w[1].1 >= 0.)
+        .max_by(|u, v| (u[1].1 - u[0].1).total_cmp(&(v[1].1 - v[0].1)))
+        .map(|w| w[0].0 + (w[1].0 - w[0].0) * w[0].1 / (w[0].1 - w[1].1))
+}
+
+/// Linear interpolation of a curve sorted by x, None outside of it.
+fn interpolate(curve: &[(f64, f64)], x: f64) -> Option<f64> {
+    let i = curve.partition_point(|p| p.0 < x);
+
+    match (i.checked_sub(1).map(|i| curve[i]), curve.get(i)) {
+        (_, Some(&(x1, y1))) if x1 == x => Some(y1),
+        (Some((x0, y0)), Some(&(x1, y1))) => Some(y0 + (y1 - y0) * (x - x0) / (x1 - x0)),
+        _ => None,
+    }
+}
+
+/// Quality of a collapse: every point is compared with every other size
+/// interpolated at the same x, S = ⟨(y - y')²⟩ / ⟨y²⟩; infinite when no
+/// two curves overlap.
+pub fn residual(curves: &[Vec<(f64, f64)>]) -> f64 {
+    let (mut sq, mut norm, mut count) = (0., 0., 0.);
+
+    for (i, a) in curves.iter().enumerate() {
+        for (j, b) in curves.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+
+            for &(x, y) in a {
+                if let Some(y_) = interpolate(b, x) {
+                    sq += (y - y_).powi(2);
+                    norm += y * y;
+                    count += 1.;
+                }
+            }
+        }
+    }
+
+    if count == 0. || norm == 0. {
+        return f64::INFINITY;
+    }
+
+    (sq / count) / (norm / count)
+}
+
+/// Minimum of f over [lo, hi], by a grid scan refined by golden sections.
+fn minimise(f: impl Fn(f64) -> f64, lo: f64, hi: f64) -> f64 {
+    let step = (hi - lo) / SCAN_STEPS as f64;
+    let best = (0..=SCAN_STEPS)
+        .map(|k| lo + k as f64 * step)
+        .min_by(|&a, &b| f(a).total_cmp(&f(b)))
+        .unwrap_or(lo);
+
+    let ratio = (5f64.sqrt() - 1.) / 2.;
+    let (mut a, mut b) = ((best - step).max(lo), (best + step).min(hi));
+
+    for _ in 0..GOLDEN_STEPS {
+        let (c, d) = (b - ratio * (b - a), a + ratio * (b - a));
+
+        if f(c) < f(d) {
+            b = d;
+        } else {
+            a = c;
+        }
+    }
+
+    (a + b) / 2.
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScalingConfig {
+    /// only |T - Tc| / Tc up to this enters the collapse
+    pub window: f64,
+}
+
+/// Collapse of one network: Tc from the Binder crossings of consecutive
+/// sizes, ν from the collapse of U4 against (T - Tc) / Tc L^{1/ν}, then β/ν
+/// from M L^{β/ν} and γ/ν from χ L^{-γ/ν} at that ν.
+#[derive(Debug, Serialize)]
+pub struct Scaling {
+    pub network: String,
+    pub sizes: Vec<u64>,
+    /// seeds averaged at every size
+    pub seeds: Vec<usize>,
+    /// (smaller size, larger size, T where their U4 cross)
+    pub crossings: Vec<(u64, u64, f64)>,
+    pub tc: f64,
+    pub tc_err: f64,
+    pub nu: f64,
+    pub beta_nu: f64,
+    pub gamma_nu: f64,
+    pub residual_binder: f64,
+    pub residual_mag: f64,
+    pub residual_susceptibility: f64,
+}
+
+impl Scaling {
+    pub fn of_curves(network: &str, curves: &[Curve], config: ScalingConfig) -> Option<Self> {
+        if curves.len() < 2 {
+            return None;
+        }
+
+        let crossings: Vec<(u64, u64, f64)> = curves
+            .windows(2)
+            .filter_map(|w| binder_crossing(&w[0], &w[1]).map(|t| (w[0].size, w[1].size, t)))
+            .collect();
+
+        if crossings.is_empty() {
+            return None;
+        }
+
+        let n = crossings.len() as f64;
+        let tc = crossings.iter().map(|c| c.2).sum::<f64>() / n;
+        let tc_err = (crossings.iter().map(|c| (c.2 - tc).powi(2)).sum::<f64>() / (n - 1.) / n).sqrt();
+
+        let scaled = |nu: f64, y: fn(&[f64; 4]) -> f64, power: f64| -> Vec<Vec<(f64, f64)>> {
+            curves
+                .iter()
+                .map(|c| {
+                    let l = c.size as f64;
+
+                    c.points
+                        .iter()
+                        .map(|p| ((p[0] - tc) / tc, p))
+                        .filter(|(t, _)| t.abs() <= config.window)
+                        .map(|(t, p)| (t * l.powf(1. / nu), y(p) * l.powf(power)))
+                        .collect()
+                })
+                .collect()
+        };
+
+        let nu = minimise(|nu| residual(&scaled(nu, |p| p[3], 0.)), 0.3, 3.);
+        let beta_nu = minimise(|b| residual(&scaled(nu, |p| p[1], b)), 0., 1.);
+        let gamma_nu = minimise(|g| residual(&scaled(nu, |p| p[2], -g)), 0., 3.);
+
+        Some(Scaling {
+            network: network.to_string(),
+            sizes: curves.iter().map(|c| c.size).collect(),
+            seeds: curves.iter().map(|c| c.seeds).collect(),
+            crossings,
+            tc,
+            tc_err,
+            nu,
+            beta_nu,
+            gamma_nu,
+            residual_binder: residual(&scaled(nu, |p| p[3], 0.)),
+            residual_mag: residual(&scaled(nu, |p| p[1], beta_nu)),
+            residual_susceptibility: residual(&scaled(nu, |p| p[2], -gamma_nu)),
+        })
+    }
+
+    /// Rows of the collapsed curves, over every T of every size.
+    pub fn save_collapse(&self, writer: &mut Writer<fs::File>, curves: &[Curve]) -> Result<(), Box<dyn Error>> {
+        for c in curves {
+            let l = c.size as f64;
+
+            for p in c.points.iter() {
+                let t = (p[0] - self.tc) / self.tc;
+
+                writer.serialize((
+                    &self.network,
+                    c.size,
+                    p[0],
+                    t * l.powf(1. / self.nu),
+                    p[3],
+                    p[1] * l.powf(self.beta_nu),
+                    p[2] * l.powf(-self.gamma_nu),
+                ))?;
+            }
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
@@ -1,27 +1,25 @@
-use std::{error::Error, path::Path, sync::mpsc::Sender};
+use std::{collections::BTreeMap, error::Error, path::Path, sync::mpsc::Sender, thread};
 
 use csv::Writer;
-use rand::{seq::SliceRandom, Rng};
+use rand::{seq::SliceRandom, Rng, RngCore, SeedableRng};
 use rand_chacha::ChaCha20Rng;
+use serde_json::Value;
 
 use crate::{
+    autocorrelation::integrated_time,
+    checkpoint::{self, Checkpoint, HysteresisState, RngState},
     child::{send, ChildMsg},
+    correlation::Correlation,
+    domains::Domains,
+    equilibrium::{equilibrate, equilibrate_with, Criterion, Equilibration, MAX_SWEEPS},
+    errors,
     frame,
-    mathy::{round_to, SIMULATION_PRECISION},
     matrix::pos_of_index,
     network::{Network, NetworkType},
+    structure::StructureFactor,
 };
 
-macro_rules! round {
-    ($x:expr) => {
-        round_to($x, SIMULATION_PRECISION)
-    };
-    ($target:expr, +, $($value: expr),+) => {
-        $target = round_to($target + $( $value )+, SIMULATION_PRECISION)
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SimulationConfig {
     pub temp: f64,
     pub h: f64,
@@ -31,6 +29,103 @@ pub struct SimulationConfig {
     pub equilibrium_steps: usize,
     pub network_type: NetworkType,
     pub eq_threshold: f64,
+    pub criterion: Criterion,
+    /// 0 keeps the serial, randomly ordered sweep; any other value sweeps the
+    /// graph colour by colour on that many threads
+    pub threads: usize,
+}
+
+pub const HYSTERESIS_COLUMNS: [&str; 28] = [
+    "t", "n", "H", "M", "E", "aE", "X", "C", "U4", "xi_k", "L", "tau_M", "tau_absM", "tau_E",
+    "samples", "domains", "largest", "walls", "sM", "M_err", "absM", "absM_err", "sE", "E_err",
+    "X_err", "C_err", "U4_err", "aM",
+];
+
+pub const PHASE_COLUMNS: [&str; 30] = [
+    "t", "n", "T", "M", "E", "aE", "X", "C", "U4", "xi", "xi_exp", "xi_k", "L", "tau_M",
+    "tau_absM", "tau_E", "samples", "domains", "largest", "walls", "sM", "M_err", "absM",
+    "absM_err", "sE", "E_err", "X_err", "C_err", "U4_err", "aM",
+];
+
+/// Columns of `series.csv`, one row per sample.
+pub const SERIES_COLUMNS: [&str; 6] = ["T", "H", "sample", "t", "E", "M"];
+
+/// What every column of the hysteresis and phase CSVs holds, written to the
+/// descriptor beside them.
+const COLUMN_DEFINITIONS: [(&str, &str); 31] = [
+    ("t", "sweeps since the run began, at the end of the point"),
+    ("n", "sweeps the point took to equilibrate"),
+    ("H", "external field"),
+    ("T", "temperature"),
+    ("M", "magnetisation per spin of the last sweep"),
+    ("E", "energy of the last sweep, -J Σ s_i s_j - H Σ s_i"),
+    ("aE", "energy averaged over every sweep of the measurement window, reset at every T and H"),
+    ("aM", "magnetisation per spin averaged over every sweep of the measurement window, reset at every T and H"),
+    ("X", "susceptibility per spin from the sampled M, about ⟨M⟩ in hysteresis and ⟨|M|⟩ in phase runs"),
+    ("C", "heat capacity per spin from the sampled E"),
+    ("U4", "Binder cumulant 1 - ⟨M⁴⟩ / 3⟨M²⟩²"),
+    ("xi", "second moment correlation length from G(r)"),
+    ("xi_exp", "exponential correlation length from G(r)"),
+    ("xi_k", "second moment correlation length from S(k), regular lattice only"),
+    ("L", "domain size from S(k), regular lattice only"),
+    ("tau_M", "integrated autocorrelation time of M, in samples"),
+    ("tau_absM", "integrated autocorrelation time of |M|, in samples"),
+    ("tau_E", "integrated autocorrelation time of E, in samples"),
+    ("samples", "samples taken at the point"),
+    ("domains", "clusters of aligned spins at the end of the point"),
+    ("largest", "fraction of the spins in the largest of them"),
+    ("walls", "bonds joining opposite spins"),
+    ("sM", "⟨M⟩ over the samples"),
+    ("M_err", "binning error of ⟨M⟩"),
+    ("absM", "⟨|M|⟩ over the samples"),
+    ("absM_err", "binning error of ⟨|M|⟩"),
+    ("sE", "⟨E⟩ over the samples"),
+    ("E_err", "binning error of ⟨E⟩"),
+    ("X_err", "jackknife error of X"),
+    ("C_err", "jackknife error of C"),
+    ("U4_err", "jackknife error of U4"),
+];
+
+/// Definitions of `columns`, in their order.
+pub fn column_definitions(columns: &[&'static str]) -> Vec<(&'static str, &'static str)> {
+    columns
+        .iter()
+        .map(|&c| (c, COLUMN_DEFINITIONS.iter().find(|d| d.0 == c).map_or("", |d| d.1)))
+        .collect()
+}
+
+/// Decimal places `x` is written with, at most 12.
+fn decimals(x: f64) -> i32 {
+    (0..12)
+        .find(|&d| {
+            let scale = 10f64.powi(d);
+
+            ((x * scale).round() / scale - x).abs() <= 1e-12 * x.abs().max(1.)
+        })
+        .unwrap_or(12)
+}
+
+/// `origin + index · step`, rounded to the decimal places of the two so that
+/// a grid with a step of 0.1 holds 0.3 and not 0.30000000000000004.
+pub fn grid_point(origin: f64, index: i64, step: f64) -> f64 {
+    let scale = 10f64.powi(decimals(origin).max(decimals(step)));
+
+    ((origin + index as f64 * step) * scale).round() / scale
+}
+
+/// How many sweeps are sampled after equilibrium at every T or H.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct MeasureConfig {
+    /// samples per block; at least one block is always taken
+    pub samples: usize,
+    /// sweeps between consecutive samples
+    pub interval: usize,
+    /// effective sample size of every observable to keep sampling for, 0 for a single block
+    pub ess: f64,
+    /// upper bound on the sweeps, whatever the effective sample size
+    pub max_sweeps: usize,
+    /// whether every sample is written to `series.csv`
+    pub series: bool,
 }
 
 #[derive(Default, Debug)]
@@ -38,6 +133,11 @@ pub struct HysteresisConfig {
     pub h_min: f64,
     pub h_max: f64,
     pub h_step: f64,
+    pub measure: MeasureConfig,
+    /// points between checkpoints, 0 for none
+    pub checkpoint_every: usize,
+    /// what the run was started with, kept in its checkpoints
+    pub run: Value,
 }
 
 #[derive(Debug)]
@@ -46,6 +146,11 @@ pub struct PhaseConfig {
     pub t_max: f64,
     pub t_step: f64,
     pub s0: f64,
+    /// steps past t_max taken at most while M stays positive
+    pub past_max: usize,
+    /// whether G(r) is measured, for xi, xi_exp and `correlation.csv`
+    pub correlation: bool,
+    pub measure: MeasureConfig,
 }
 
 #[derive(Debug)]
@@ -55,42 +160,218 @@ pub struct Simulation {
     pub time: u128,
     pub n: u128,
     pub spin_sum: i64,
-    pub ham_internal: f64,
-    pub ham_external: f64,
+    /// Σ s_i s_j over every bond, counted once
+    pub bond_sum: i64,
+    /// exchange and field energies averaged over the measurement window
     pub ham_agr_internal: f64,
     pub ham_agr_external: f64,
+    /// magnetisation averaged over the same window
+    pub mag_agr: f64,
+    /// sweeps in the averages so far
+    pub agr_sweeps: u128,
     pub name: String,
     pub tx: Sender<ChildMsg>,
     pub dist: String,
     pub free_count: i64,
-    action_log: Vec<String>,
+    max_deg: i64,
+    acceptance: Vec<f64>,
+    colours: Vec<Vec<usize>>,
+    /// moments of the sweeps sampled at the current T and H
+    pub moments: Accumulator,
+    /// G(r) of the same sweeps, when it's being measured
+    pub correlation: Option<Correlation>,
+    /// S(k) of the same sweeps, regular lattice only
+    pub structure: Option<StructureFactor>,
+}
+
+/// Drives a `Simulation` a sweep at a time. Every sweep leaves `spin_sum`
+/// and `bond_sum` current; an engine that keeps the spins in a form of its
+/// own writes them back in `sync`, which is called before they are read.
+pub trait Engine {
+    fn sweep(&mut self, s: &mut Simulation, rng: &mut ChaCha20Rng);
+
+    fn sync(&mut self, _s: &mut Simulation) {}
+}
+
+impl<F: FnMut(&mut Simulation, &mut ChaCha20Rng)> Engine for F {
+    fn sweep(&mut self, s: &mut Simulation, rng: &mut ChaCha20Rng) {
+        self(s, rng)
+    }
+}
+
+/// Running sums of M, |M|, M², M⁴, E and E² over sampled sweeps, along
+/// with the per-sweep (M, E) series.
+#[derive(Debug, Default, Clone)]
+pub struct Accumulator {
+    pub samples: f64,
+    pub m: f64,
+    pub m_abs: f64,
+    pub m2: f64,
+    pub m4: f64,
+    pub e: f64,
+    pub e2: f64,
+    pub series: Vec<(f64, f64)>,
+}
+
+impl Accumulator {
+    pub fn push(&mut self, m: f64, e: f64) {
+        self.series.push((m, e));
+        self.samples += 1.;
+        self.m += m;
+        self.m_abs += m.abs();
+        self.m2 += m * m;
+        self.m4 += m * m * m * m;
+        self.e += e;
+        self.e2 += e * e;
+    }
+
+    fn mean(&self, sum: f64) -> f64 {
+        sum / self.samples.max(1.)
+    }
+
+    pub fn mag(&self) -> f64 {
+        self.mean(self.m)
+    }
+
+    pub fn mag_abs(&self) -> f64 {
+        self.mean(self.m_abs)
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.mean(self.e)
+    }
+
+    /// Specific heat per spin, (⟨E²⟩ - ⟨E⟩²) / (kB T² N).
+    pub fn heat(&self, s: &Simulation) -> f64 {
+        let e = self.energy();
+
+        (self.mean(self.e2) - e * e)
+            / (s.config.kb * s.config.temp * s.config.temp * s.network.size2)
+    }
+
+    /// Susceptibility per spin from the spread of |M|, which is what a finite
+    /// system at H = 0 measures, since the sign of M wanders.
+    pub fn susceptibility(&self, s: &Simulation) -> f64 {
+        let m_abs = self.mag_abs();
+
+        s.network.size2 * (self.mean(self.m2) - m_abs * m_abs) / (s.config.kb * s.config.temp)
+    }
+
+    /// Susceptibility per spin from the spread of M itself, for a field that
+    /// pins the sign.
+    pub fn susceptibility_signed(&self, s: &Simulation) -> f64 {
+        let m = self.mag();
+
+        s.network.size2 * (self.mean(self.m2) - m * m) / (s.config.kb * s.config.temp)
+    }
+
+    /// Integrated autocorrelation times of M, |M| and E, in samples.
+    pub fn autocorrelation_times(&self) -> [f64; 3] {
+        let m: Vec<f64> = self.series.iter().map(|p| p.0).collect();
+        let m_abs: Vec<f64> = m.iter().map(|m| m.abs()).collect();
+        let e: Vec<f64> = self.series.iter().map(|p| p.1).collect();
+
+        [&m, &m_abs, &e].map(|xs| integrated_time(xs).0)
+    }
+
+    /// Effective number of independent samples, N / 2τ_int, of the slowest observable.
+    pub fn effective_samples(&self) -> f64 {
+        let tau = self.autocorrelation_times().into_iter().fold(0.5, f64::max);
+
+        self.samples / (2. * tau)
+    }
+
+    /// Binder cumulant U_4 = 1 - ⟨M⁴⟩ / 3⟨M²⟩².
+    pub fn binder(&self) -> f64 {
+        let m2 = self.mean(self.m2);
+
+        1. - self.mean(self.m4) / (3. * m2 * m2)
+    }
+
+    /// The sums with those of `other`, a part of the same series, taken out.
+    fn without(&self, other: &Accumulator) -> Accumulator {
+        Accumulator {
+            samples: self.samples - other.samples,
+            m: self.m - other.m,
+            m_abs: self.m_abs - other.m_abs,
+            m2: self.m2 - other.m2,
+            m4: self.m4 - other.m4,
+            e: self.e - other.e,
+            e2: self.e2 - other.e2,
+            series: vec![],
+        }
+    }
+
+    /// Errors of ⟨M⟩, ⟨|M|⟩ and ⟨E⟩ by binning, and of χ, C and U_4 by a
+    /// jackknife over contiguous blocks of the series; χ from the spread of
+    /// M when `signed`, of |M| otherwise.
+    pub fn errors(&self, s: &Simulation, signed: bool) -> [f64; 6] {
+        let m: Vec<f64> = self.series.iter().map(|p| p.0).collect();
+        let m_abs: Vec<f64> = m.iter().map(|m| m.abs()).collect();
+        let e: Vec<f64> = self.series.iter().map(|p| p.1).collect();
+
+        let len = self.series.len().div_ceil(errors::JACKKNIFE_BLOCKS).max(1);
+        let leave_outs: Vec<Accumulator> = self
+            .series
+            .chunks(len)
+            .map(|block| {
+                let mut acc = Accumulator::default();
+                block.iter().for_each(|&(m, e)| acc.push(m, e));
+
+                self.without(&acc)
+            })
+            .collect();
+
+        let jackknife = |f: &dyn Fn(&Accumulator) -> f64| match leave_outs.len() {
+            0 | 1 => f64::NAN,
+            _ => errors::jackknife(&leave_outs.iter().map(f).collect::<Vec<f64>>()),
+        };
+
+        [
+            errors::binning(&m),
+            errors::binning(&m_abs),
+            errors::binning(&e),
+            jackknife(&|a| match signed {
+                true => a.susceptibility_signed(s),
+                false => a.susceptibility(s),
+            }),
+            jackknife(&|a| a.heat(s)),
+            jackknife(&|a| a.binder()),
+        ]
+    }
+
+    /// ⟨M⟩, ⟨|M|⟩ and ⟨E⟩ each followed by its error, then the errors of χ,
+    /// C and U_4: the sM to U4_err columns of the CSVs.
+    pub fn estimates(&self, s: &Simulation, signed: bool) -> [f64; 9] {
+        let [m_err, m_abs_err, e_err, x_err, c_err, u_err] = self.errors(s, signed);
+
+        [self.mag(), m_err, self.mag_abs(), m_abs_err, self.energy(), e_err, x_err, c_err, u_err]
+    }
+}
+
+/// Appends how the point at `x`, T or H, was equilibrated.
+pub fn save_equilibration(writer: &mut Writer<std::fs::File>, x: f64, e: Equilibration) -> Result<(), Box<dyn Error>> {
+    writer.serialize((x, e.criterion.to_string(), e.fired, e.sweeps))?;
+    writer.flush()?;
+
+    Ok(())
 }
 
 #[derive(Debug)]
 pub struct StateSnapshot {
     pub spin_sum: i64,
-    pub ham_internal: f64,
-    pub ham_external: f64,
-    pub ham_agr_internal: f64,
-    pub ham_agr_external: f64,
+    pub ham: f64,
     pub mag: f64,
 }
 
 impl StateSnapshot {
-    pub fn of_simulation<'a>(simulation: &'a Simulation) -> Self {
+    pub fn of_simulation(simulation: &Simulation) -> Self {
         StateSnapshot {
             spin_sum: simulation.spin_sum,
-            ham_internal: simulation.ham_internal,
-            ham_external: simulation.ham_external,
-            ham_agr_internal: simulation.ham_agr_internal,
-            ham_agr_external: simulation.ham_agr_external,
+            ham: simulation.ham(),
             mag: simulation.mag(),
         }
     }
-
-    pub fn ham(&self) -> f64 {
-        round_to(self.ham_internal + self.ham_external, SIMULATION_PRECISION)
-    }
 }
 
 impl Simulation {
@@ -103,90 +384,151 @@ impl Simulation {
         dist: String,
     ) -> Self {
         let network = Network::new(size, &config.network_type, rand);
+
+        Simulation::of_network(network, config, name, tx, dist)
+    }
+
+    pub fn of_network(
+        network: Network,
+        config: SimulationConfig,
+        name: String,
+        tx: Sender<ChildMsg>,
+        dist: String,
+    ) -> Self {
+        let max_deg = network.lattice.iter().map(|ns| ns.len()).max().unwrap_or(0) as i64;
+        let colours = network.colouring();
         let mut s = Simulation {
             network,
             config,
             time: 0,
             spin_sum: 0,
-            ham_internal: 0.,
-            ham_external: 0.,
+            bond_sum: 0,
             ham_agr_internal: 0.,
             ham_agr_external: 0.,
+            mag_agr: 0.,
+            agr_sweeps: 0,
             name,
             tx,
             n: 0,
             dist,
             free_count: 0,
-            action_log: vec![],
+            max_deg,
+            acceptance: vec![],
+            colours,
+            moments: Accumulator::default(),
+            correlation: None,
+            structure: None,
         };
 
         s.free_count = s
             .network
             .lattice
             .enumerator()
-            .filter(|(_, ns)| ns.len() == 0)
+            .filter(|(_, ns)| ns.is_empty())
             .count() as i64;
+        s.rebuild_acceptance();
 
         s
     }
 
-    pub fn mag(&self) -> f64 {
-        round_to(
-            (self.spin_sum as f64) / (self.network.size.pow(2) as f64),
-            SIMULATION_PRECISION,
-        )
+    /// Makes a copy of the simulation sharing the same graph, with freshly
+    /// randomised spins, running at the temperature `temp`.
+    pub fn replica(&self, temp: f64, rand: &mut ChaCha20Rng) -> Self {
+        let mut network = self.network.clone();
+        network.randomise_spins(rand);
+
+        let mut s = Simulation::of_network(
+            network,
+            SimulationConfig {
+                temp,
+                ..self.config.clone()
+            },
+            self.name.to_owned(),
+            self.tx.clone(),
+            self.dist.to_owned(),
+        );
+        s.refresh_measurements();
+
+        s
     }
 
-    pub fn ham(&self) -> f64 {
-        round_to(self.ham_internal + self.ham_external, SIMULATION_PRECISION)
+    /// Exchanges spin configurations (and the energies derived from them)
+    /// with `other`, leaving both temperatures in place.
+    pub fn swap_state(&mut self, other: &mut Simulation) {
+        std::mem::swap(&mut self.network.spins, &mut other.network.spins);
+        std::mem::swap(&mut self.spin_sum, &mut other.spin_sum);
+        std::mem::swap(&mut self.bond_sum, &mut other.bond_sum);
+        std::mem::swap(&mut self.ham_agr_internal, &mut other.ham_agr_internal);
+        std::mem::swap(&mut self.ham_agr_external, &mut other.ham_agr_external);
+        std::mem::swap(&mut self.mag_agr, &mut other.mag_agr);
+        std::mem::swap(&mut self.agr_sweeps, &mut other.agr_sweeps);
     }
 
-    pub fn ham_agr(&self) -> f64 {
-        round_to(
-            self.ham_agr_internal + self.ham_agr_external,
-            SIMULATION_PRECISION,
-        )
+    pub fn set_temp(&mut self, temp: f64) {
+        self.config.temp = temp;
+        self.rebuild_acceptance();
     }
 
-    fn calc_h_internal(&self) -> f64 {
-        round!(
-            -self.config.j / 2.
-                * self
-                    .network
-                    .spins
-                    .enumerator()
-                    .map(|(p, &s)| {
-                        let neighbour_spin_sum =
-                            self.network.get_neighbours(p).iter().sum::<i8>() as i64;
+    pub fn set_field(&mut self, h: f64) {
+        self.config.h = h;
+        self.rebuild_acceptance();
+    }
 
-                        (s as i64) * neighbour_spin_sum
-                    })
-                    .sum::<i64>() as f64
-        )
+    fn acceptance_index(&self, field: i64, spin: i8) -> usize {
+        (2 * (field + self.max_deg) + (spin > 0) as i64) as usize
     }
 
-    fn calc_h_external(&self) -> f64 {
-        round!(-self.config.h * self.network.spins.iter().sum::<i8>() as f64)
+    /// Tabulates min(1, exp(-ΔE / kT)) for every (local field, spin) pair;
+    /// has to be rebuilt whenever T or H change.
+    fn rebuild_acceptance(&mut self) {
+        let kt = self.config.kb * self.config.temp;
+        let fields = -self.max_deg..=self.max_deg;
+
+        self.acceptance = fields
+            .flat_map(|field| [-1i8, 1i8].map(|spin| (field, spin)))
+            .map(|(field, spin)| {
+                let d_ham = self.delta_ham(field, spin);
+
+                if d_ham <= 0. {
+                    1.
+                } else {
+                    (-d_ham / kt).exp()
+                }
+            })
+            .collect();
     }
 
-    fn calc_delta_h_internal(&self, p: (usize, usize)) -> f64 {
-        let sk = self.network.get_spin(p) as f64;
+    pub fn mag(&self) -> f64 {
+        (self.spin_sum as f64) / self.network.size2
+    }
 
-        round!(sk * 2. * self.config.j * (self.network.get_neighbours(p).iter().sum::<i8>() as f64))
+    pub fn ham_internal(&self) -> f64 {
+        -self.config.j * self.bond_sum as f64
     }
 
-    fn calc_delta_h_external(&mut self, p: (usize, usize)) -> f64 {
-        let sk = self.network.get_spin(p) as f64;
+    pub fn ham_external(&self) -> f64 {
+        -self.config.h * self.spin_sum as f64
+    }
 
-        round!(sk * 2. * self.config.h)
+    pub fn ham(&self) -> f64 {
+        self.ham_internal() + self.ham_external()
     }
 
-    pub fn calc_delta_h(&mut self, p: (usize, usize)) -> f64 {
-        let d_int = self.calc_delta_h_internal(p);
-        let d_ext = self.calc_delta_h_external(p);
-        let d_ham = round_to(d_int + d_ext, SIMULATION_PRECISION);
+    pub fn ham_agr(&self) -> f64 {
+        self.ham_agr_internal + self.ham_agr_external
+    }
+
+    fn calc_bond_sum(&self) -> i64 {
+        self.network
+            .spins
+            .enumerator()
+            .map(|(p, &s)| (s as i64) * self.network.local_field(p))
+            .sum::<i64>()
+            / 2
+    }
 
-        d_ham
+    fn delta_ham(&self, field: i64, spin: i8) -> f64 {
+        2. * spin as f64 * (self.config.j * field as f64 + self.config.h)
     }
 
     pub fn calc_magnetisation(&mut self) -> f64 {
@@ -195,62 +537,115 @@ impl Simulation {
         self.mag()
     }
 
-    fn assert_correctness(
-        &self,
-        prev: &StateSnapshot,
-        p: (usize, usize),
-        d_int: f64,
-        d_ext: f64,
-        d_ham: f64,
-    ) {
-        assert_eq!(
-            round!(self.ham_internal - prev.ham_internal),
-            d_int,
-            "Invalid ham internal {:?} (={}) {:?}; logs: {:?}",
-            p,
-            self.network.get_spin(p),
-            self.network.get_neighbours(p),
-            self.action_log
-        );
-        assert_eq!(
-            round!(self.ham_external - prev.ham_external),
-            d_ext,
-            "Invalid ham external {:?} (={}) {:?}; Σ: {}, Σprev: {}; logs: {:?}",
-            p,
-            self.network.get_spin(p),
-            self.network.get_neighbours(p),
-            self.network.spins.iter().sum::<i8>(),
-            prev.spin_sum,
-            self.action_log
-                .iter()
-                .fold("".to_string(), |u, l| { format!("{}\n{}", u, l) })
-        );
-        assert_eq!(
-            round!(self.ham() - prev.ham()),
-            d_ham,
-            "Invalid ham: {:?} (={}) {:?}; logs: {:?}",
-            p,
-            self.network.get_spin(p),
-            self.network.get_neighbours(p),
-            self.action_log
-        );
+    /// Recomputes the energies and magnetisation from the current spins.
+    pub fn refresh_measurements(&mut self) {
+        self.bond_sum = self.calc_bond_sum();
+        self.calc_magnetisation();
+    }
+
+    /// Adds the current sweep to the running averages of the energies and M.
+    pub fn aggregate(&mut self) {
+        self.agr_sweeps += 1;
+        let n = self.agr_sweeps as f64;
+
+        self.ham_agr_internal += (self.ham_internal() - self.ham_agr_internal) / n;
+        self.ham_agr_external += (self.ham_external() - self.ham_agr_external) / n;
+        self.mag_agr += (self.mag() - self.mag_agr) / n;
     }
 
     fn evolve_spin(&mut self, p: (usize, usize), rng: &mut ChaCha20Rng) {
-        let d_int = self.calc_delta_h_internal(p);
-        let d_ext = self.calc_delta_h_external(p);
-        let d_ham = self.calc_delta_h(p);
+        let spin = self.network.get_spin(p);
+        let field = self.network.local_field(p);
         let distortion = rng.gen::<f64>();
-        let v = (-d_ham / (self.config.kb * self.config.temp)).exp();
 
-        if d_ham <= 0. || distortion < v {
-            self.spin_sum += 2 * self.network.flip_spin(p) as i64;
-            self.ham_internal = round!(self.ham_internal + d_int);
-            self.ham_external = round!(self.ham_external + d_ext);
+        if distortion < self.acceptance[self.acceptance_index(field, spin)] {
+            self.flip(p);
+        }
+    }
+
+    /// Flips the spin at `p`, keeping the energy and magnetisation in sync.
+    pub fn flip(&mut self, p: (usize, usize)) {
+        let spin = self.network.get_spin(p) as i64;
+
+        self.bond_sum -= 2 * spin * self.network.local_field(p);
+        self.spin_sum -= 2 * spin;
+        self.network.flip_spin(p);
+    }
+
+    /// Number of distinct (local field, spin) flip classes.
+    pub fn flip_classes(&self) -> usize {
+        self.acceptance.len()
+    }
+
+    pub fn flip_class(&self, p: (usize, usize)) -> usize {
+        self.acceptance_index(self.network.local_field(p), self.network.get_spin(p))
+    }
+
+    /// Metropolis acceptance probability of a spin in flip class `class`.
+    pub fn flip_rate(&self, class: usize) -> f64 {
+        self.acceptance[class]
+    }
+
+    /// Sweeps one colour class at a time. Sites of the same colour share no
+    /// bonds, so their updates are split between threads; every site draws
+    /// from its own position of a per-sweep ChaCha stream, which keeps the
+    /// result independent of the thread count.
+    fn mc_iter_coloured(&mut self, rng: &mut ChaCha20Rng) {
+        let key = rng.next_u64();
+        let threads = self.config.threads;
+
+        for class in 0..self.colours.len() {
+            let flips: Vec<usize> = {
+                let network = &self.network;
+                let acceptance = &self.acceptance;
+                let max_deg = self.max_deg;
+                let sites = &self.colours[class];
+                let chunk = sites.len().div_ceil(threads).max(1);
+
+                thread::scope(|scope| {
+                    let handles: Vec<_> = sites
+                        .chunks(chunk)
+                        .map(|part| {
+                            scope.spawn(move || {
+                                let mut site_rng = ChaCha20Rng::seed_from_u64(key);
+
+                                part.iter()
+                                    .copied()
+                                    .filter(|&i| {
+                                        let spin = network.spins[i];
+                                        let field =
+                                            network.local_field(pos_of_index(network.size, i));
+
+                                        site_rng.set_word_pos(2 * i as u128);
+
+                                        site_rng.gen::<f64>()
+                                            < acceptance[(2 * (field + max_deg)
+                                                + (spin > 0) as i64)
+                                                as usize]
+                                    })
+                                    .collect::<Vec<usize>>()
+                            })
+                        })
+                        .collect();
+
+                    handles
+                        .into_iter()
+                        .flat_map(|h| h.join().unwrap())
+                        .collect()
+                })
+            };
+
+            for i in flips {
+                self.flip(pos_of_index(self.network.size, i));
+            }
         }
     }
 
     pub fn mc_iter(&mut self, rng: &mut ChaCha20Rng) {
+        if self.config.threads > 0 {
+            return self.mc_iter_coloured(rng);
+        }
+
         let mut indices = (0..self.network.size.pow(2)).collect::<Vec<usize>>();
         indices.shuffle(rng);
 
@@ -259,9 +654,115 @@ impl Simulation {
         }
     }
 
+    /// Clears everything collected at the previous T and H.
+    pub fn reset_samples(&mut self) {
+        self.moments = Accumulator::default();
+        self.agr_sweeps = 0;
+        self.ham_agr_internal = 0.;
+        self.ham_agr_external = 0.;
+        self.mag_agr = 0.;
+        if let Some(c) = self.correlation.as_mut() {
+            c.reset();
+        }
+        if let Some(sf) = self.structure.as_mut() {
+            sf.reset();
+        }
+    }
+
+    /// Adds the current configuration to the moments, G(r) and S(k).
+    pub fn sample(&mut self) {
+        let (m, e) = (self.mag(), self.ham());
+        self.moments.push(m, e);
+
+        if let Some(sf) = self.structure.as_mut() {
+            sf.push(&self.network);
+        }
+        if let Some(c) = self.correlation.as_mut() {
+            c.push(&self.network, self.structure.as_ref());
+        }
+    }
+
+    /// Samples every `config.interval` sweeps of `engine`, one block of
+    /// `config.samples` at a time, until the effective sample size reaches
+    /// `config.ess`.
+    pub fn measure_with(&mut self, config: &MeasureConfig, rng: &mut ChaCha20Rng, engine: &mut impl Engine) {
+        self.reset_samples();
+
+        loop {
+            for _ in 0..config.samples {
+                for _ in 0..config.interval.max(1) {
+                    engine.sweep(self, rng);
+                    self.time += 1;
+                    self.aggregate();
+                }
+                engine.sync(self);
+                self.sample();
+            }
+
+            let taken = self.moments.samples as usize * config.interval.max(1);
+
+            if config.samples == 0
+                || config.ess <= 0.
+                || taken >= config.max_sweeps
+                || self.moments.effective_samples() >= config.ess
+            {
+                break;
+            }
+        }
+    }
+
+    pub fn measure(&mut self, config: &MeasureConfig, rng: &mut ChaCha20Rng) {
+        self.measure_with(config, rng, &mut |s: &mut Simulation, rng: &mut ChaCha20Rng| s.mc_iter(rng));
+    }
+
+    /// Second moment length and domain size from S(k), NaN off the regular lattice.
+    fn structure_lengths(&self) -> [f64; 2] {
+        self.structure.as_ref().map_or([f64::NAN; 2], |sf| {
+            [sf.second_moment_length(), sf.domain_size()]
+        })
+    }
+
+    /// Appends the domain size distribution at the current point, `x` being T or H.
+    fn save_domains(&self, writer: &mut Writer<std::fs::File>, x: f64) -> Result<(), Box<dyn Error>> {
+        for (size, count) in Domains::of_network(&self.network).distribution() {
+            writer.serialize((x, size, count))?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Appends every sample taken at the current T and H, numbered from 0
+    /// at every point, with the sweep it was taken at.
+    pub fn save_series(&self, writer: &mut Writer<std::fs::File>, config: &MeasureConfig) -> Result<(), Box<dyn Error>> {
+        let last = self.moments.series.len();
+
+        for (k, &(m, e)) in self.moments.series.iter().enumerate() {
+            let t = self.time - ((last - 1 - k) * config.interval.max(1)) as u128;
+            writer.serialize((self.config.temp, self.config.h, k, t, e, m))?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Appends the radially averaged S(k) at the current point, `x` being T or H.
+    fn save_structure(&self, writer: &mut Writer<std::fs::File>, x: f64) -> Result<(), Box<dyn Error>> {
+        if let Some(sf) = self.structure.as_ref() {
+            for (&k, s) in sf.wavenumbers.iter().zip(sf.function()) {
+                writer.serialize((x, k, s))?;
+            }
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+
     pub fn snapshot_hysteresis(&self) -> Result<Vec<f64>, Box<dyn Error>> {
         let h = self.config.h;
         let m = self.mag();
+        let tau = self.moments.autocorrelation_times();
+        let domains = Domains::of_network(&self.network);
 
         send!(
             self.tx,
@@ -279,19 +780,37 @@ impl Simulation {
             )
         );
 
-        Ok(vec![
+        let mut row = vec![
             self.time as f64,
             self.n as f64,
             h,
             m,
             self.ham(),
             self.ham_agr(),
-        ])
+            self.moments.susceptibility_signed(self),
+            self.moments.heat(self),
+            self.moments.binder(),
+            self.structure_lengths()[0],
+            self.structure_lengths()[1],
+            tau[0],
+            tau[1],
+            tau[2],
+            self.moments.samples,
+            domains.count() as f64,
+            domains.largest_fraction(),
+            domains.walls as f64,
+        ];
+        row.extend(self.moments.estimates(self, true));
+        row.push(self.mag_agr);
+
+        Ok(row)
     }
 
     pub fn snapshot_phase(&self) -> Result<Vec<f64>, Box<dyn Error>> {
         let temp = self.config.temp;
         let m = self.mag();
+        let tau = self.moments.autocorrelation_times();
+        let domains = Domains::of_network(&self.network);
 
         send!(
             self.tx,
@@ -309,19 +828,41 @@ impl Simulation {
             )
         );
 
-        Ok(vec![
+        let mut row = vec![
             self.time as f64,
             self.n as f64,
             temp,
             m,
             self.ham(),
             self.ham_agr(),
-        ])
+            self.moments.susceptibility(self),
+            self.moments.heat(self),
+            self.moments.binder(),
+            self.correlation
+                .as_ref()
+                .map_or(f64::NAN, |c| c.second_moment_length()),
+            self.correlation
+                .as_ref()
+                .map_or(f64::NAN, |c| c.exponential_length()),
+            self.structure_lengths()[0],
+            self.structure_lengths()[1],
+            tau[0],
+            tau[1],
+            tau[2],
+            self.moments.samples,
+            domains.count() as f64,
+            domains.largest_fraction(),
+            domains.walls as f64,
+        ];
+        row.extend(self.moments.estimates(self, false));
+        row.push(self.mag_agr);
+
+        Ok(row)
     }
 
     pub fn is_at_equilibrium(&self, prev_state: &StateSnapshot) -> bool {
         let d_count = (prev_state.spin_sum - self.spin_sum).abs();
-        let d_ham = (prev_state.ham() - self.ham()).abs();
+        let d_ham = (prev_state.ham - self.ham()).abs();
 
         let ham_relax = d_ham < f64::MIN_POSITIVE;
         let mag_relax = d_count == 2 * self.free_count
@@ -338,77 +879,107 @@ impl Simulation {
         mag_relax && ham_relax
     }
 
+    /// Puts back the spins, times, T and H of a checkpoint.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        for (i, &spin) in checkpoint.spins.iter().enumerate() {
+            self.network.spins[i] = spin;
+        }
+
+        self.refresh_measurements();
+        self.time = checkpoint.time;
+        self.n = checkpoint.n;
+        self.set_temp(checkpoint.temp);
+        self.set_field(checkpoint.h);
+    }
+
+    /// Runs the loop from `resume` when given, writing a checkpoint every
+    /// `config.checkpoint_every` points.
     pub fn simulate_hysteresis(
         &mut self,
         data_dist_path: &Path,
         config: HysteresisConfig,
         rand: &mut ChaCha20Rng,
+        resume: Option<Checkpoint>,
     ) -> Result<(), Box<dyn Error>> {
-        let mut data_writer = Writer::from_path(data_dist_path)?;
-        // Write header
-        data_writer.write_record(&["t", "n", "H", "M", "E", "aE"])?;
-        data_writer.flush()?;
-
-        self.ham_internal = self.calc_h_internal();
-        self.ham_agr_internal = self.ham_internal;
-
-        self.ham_external = self.calc_h_external();
-        self.ham_agr_external = self.ham_external;
-
-        self.calc_magnetisation();
-
-        let mut saw_max = false;
-        let mut step_direction = 1f64;
+        let dist = Path::new(&self.dist).to_path_buf();
+        let structure_path = dist.join("structure.csv");
+        let domains_path = dist.join("domains.csv");
+        let series_path = dist.join("series.csv");
+        let equilibrium_path = dist.join("equilibrium.csv");
+
+        let mut data_writer = checkpoint::writer(data_dist_path, &HYSTERESIS_COLUMNS, resume.as_ref())?;
+        let mut structure_writer = checkpoint::writer(&structure_path, &["H", "k", "S"], resume.as_ref())?;
+        let mut domains_writer = checkpoint::writer(&domains_path, &["H", "size", "count"], resume.as_ref())?;
+        let mut series_writer = match config.measure.series {
+            true => Some(checkpoint::writer(&series_path, &SERIES_COLUMNS, resume.as_ref())?),
+            false => None,
+        };
+        let mut equilibrium_writer = checkpoint::writer(
+            &equilibrium_path,
+            &["H", "criterion", "fired", "sweeps"],
+            resume.as_ref(),
+        )?;
+
+        if let NetworkType::Regular = self.config.network_type {
+            self.structure = Some(StructureFactor::new(self.network.size));
+        }
 
-        let mut prev_time = 0;
-        self.time = 0;
+        self.refresh_measurements();
 
-        loop {
-            let prev_state = StateSnapshot::of_simulation(&self);
+        // the field is kept as an integer number of steps, so that it never drifts
+        let h_index_max = (config.h_max / config.h_step).round() as i64;
+        let h_index_min = (config.h_min / config.h_step).round() as i64;
 
-            self.mc_iter(rand);
+        let mut state = match resume.as_ref() {
+            Some(c) => {
+                self.restore(c);
+                *rand = c.rng.rng();
 
-            if self.is_at_equilibrium(&prev_state) {
-                break;
+                c.state
             }
-        }
+            None => {
+                self.time = 0;
+                equilibrate(self, MAX_SWEEPS, rand, |_, _| Ok(()))?;
+                self.time = 0;
+
+                HysteresisState {
+                    h_index: (self.config.h / config.h_step).round() as i64,
+                    step_direction: 1,
+                    saw_max: false,
+                    prev_time: 0,
+                    points: 0,
+                }
+            }
+        };
 
-        while !(self.config.h >= config.h_max && saw_max) {
-            let is_max = self.config.h >= config.h_max || self.config.h <= config.h_min;
+        while !(state.h_index >= h_index_max && state.saw_max) {
+            let is_max = state.h_index >= h_index_max || state.h_index <= h_index_min;
             self.n = 0;
 
             if is_max {
-                step_direction *= -1f64;
+                state.step_direction *= -1;
             }
 
-            saw_max |= is_max;
+            state.saw_max |= is_max;
 
             // simulate
-            loop {
-                let prev_state = StateSnapshot::of_simulation(&self);
+            let equilibration = equilibrate(self, MAX_SWEEPS, rand, |_, _| Ok(()))?;
+            save_equilibration(&mut equilibrium_writer, self.config.h, equilibration)?;
 
-                self.mc_iter(rand);
-
-                self.time += 1;
-                self.n += 1;
-
-                if self.is_at_equilibrium(&prev_state) || self.n > (1e8 as u128) {
-                    break;
-                }
-            }
+            // sample
+            self.measure(&config.measure, rand);
 
             // update measurements
-            self.ham_internal = self.calc_h_internal();
-            self.ham_agr_internal = self.ham_internal;
-
-            self.ham_external = self.calc_h_external();
-            self.ham_agr_external = self.ham_external;
-
-            self.calc_magnetisation();
+            self.refresh_measurements();
 
             // save
             data_writer.serialize(self.snapshot_hysteresis()?)?;
-            
+            self.save_structure(&mut structure_writer, self.config.h)?;
+            self.save_domains(&mut domains_writer, self.config.h)?;
+            if let Some(writer) = series_writer.as_mut() {
+                self.save_series(writer, &config.measure)?;
+            }
+
             // plot frame
             frame!(
                 self,
@@ -416,7 +987,7 @@ impl Simulation {
                     "H: {}, t: {} (Δt: {}), M: {}, T: {}, N: {}, <E>: {}",
                     self.config.h,
                     self.time,
-                    self.time - prev_time,
+                    self.time - state.prev_time,
                     self.mag(),
                     self.config.temp,
                     self.network.size,
@@ -424,13 +995,36 @@ impl Simulation {
                 )
             );
 
-            prev_time = self.time;
+            state.prev_time = self.time;
+            state.points += 1;
 
             // step
-            self.config.h = round_to(
-                self.config.h + round_to(step_direction * config.h_step, SIMULATION_PRECISION),
-                SIMULATION_PRECISION,
-            );
+            state.h_index += state.step_direction;
+            self.set_field(grid_point(0., state.h_index, config.h_step));
+
+            if config.checkpoint_every > 0 && state.points % config.checkpoint_every == 0 {
+                let mut offsets = BTreeMap::new();
+                checkpoint::record_offset(&mut offsets, data_dist_path, &mut data_writer)?;
+                checkpoint::record_offset(&mut offsets, &structure_path, &mut structure_writer)?;
+                checkpoint::record_offset(&mut offsets, &domains_path, &mut domains_writer)?;
+                if let Some(writer) = series_writer.as_mut() {
+                    checkpoint::record_offset(&mut offsets, &series_path, writer)?;
+                }
+                checkpoint::record_offset(&mut offsets, &equilibrium_path, &mut equilibrium_writer)?;
+
+                Checkpoint {
+                    run: config.run.clone(),
+                    spins: self.network.spins.iter().copied().collect(),
+                    time: self.time,
+                    n: self.n,
+                    temp: self.config.temp,
+                    h: self.config.h,
+                    state,
+                    rng: RngState::of_rng(rand),
+                    offsets,
+                }
+                .save(&self.dist)?;
+            }
         }
 
         data_writer.flush()?;
@@ -443,71 +1037,124 @@ impl Simulation {
         data_dist_path: &Path,
         config: PhaseConfig,
         rand: &mut ChaCha20Rng,
+    ) -> Result<(), Box<dyn Error>> {
+        self.simulate_phase_with(data_dist_path, config, rand, |s: &mut Simulation, rng: &mut ChaCha20Rng| {
+            s.mc_iter(rng)
+        })
+    }
+
+    /// `simulate_phase` with every sweep made by `engine`.
+    pub fn simulate_phase_with(
+        &mut self,
+        data_dist_path: &Path,
+        config: PhaseConfig,
+        rand: &mut ChaCha20Rng,
+        mut engine: impl Engine,
     ) -> Result<(), Box<dyn Error>> {
         let mut data_writer = Writer::from_path(data_dist_path)?;
         // Write header
-        data_writer.write_record(&["t", "n", "T", "M", "E", "aE"])?;
+        data_writer.write_record(PHASE_COLUMNS)?;
         data_writer.flush()?;
 
+        let mut structure_writer = Writer::from_path(format!("{}/structure.csv", self.dist))?;
+        structure_writer.write_record(["T", "k", "S"])?;
+
+        let mut domains_writer = Writer::from_path(format!("{}/domains.csv", self.dist))?;
+        domains_writer.write_record(["T", "size", "count"])?;
+
+        let mut series_writer = match config.measure.series {
+            true => {
+                let mut writer = Writer::from_path(format!("{}/series.csv", self.dist))?;
+                writer.write_record(SERIES_COLUMNS)?;
+
+                Some(writer)
+            }
+            false => None,
+        };
+
+        let mut equilibrium_writer = Writer::from_path(format!("{}/equilibrium.csv", self.dist))?;
+        equilibrium_writer.write_record(["T", "criterion", "fired", "sweeps"])?;
+
+        if let NetworkType::Regular = self.config.network_type {
+            self.structure = Some(StructureFactor::new(self.network.size));
+        }
+
+        let mut correlation_writer = match config.correlation {
+            true => {
+                let mut writer = Writer::from_path(format!("{}/correlation.csv", self.dist))?;
+                writer.write_record(["T", "r", "pairs", "G"])?;
+
+                Some(writer)
+            }
+            false => None,
+        };
+
+        self.correlation = match config.correlation {
+            true => Some(Correlation::new(&self.network, self.config.network_type)),
+            false => None,
+        };
+
         for x in 0..self.network.size {
             for y in 0..self.network.size {
                 self.network.spins[(x, y)] = 1;
             }
         }
 
-        self.ham_internal = self.calc_h_internal();
-        self.ham_agr_internal = self.ham_internal;
-
-        self.ham_external = self.calc_h_external();
-        self.ham_agr_external = self.ham_external;
-
-        self.calc_magnetisation();
+        self.set_temp(config.t_min);
+        self.refresh_measurements();
 
         let mut prev_time = self.time;
+        let mut t_index = 0;
+        let steps = ((config.t_max - config.t_min) / config.t_step).round() as usize;
 
-        while self.mag() >= 0. {
+        // up to t_max whatever the sign of M, which finite size scaling needs
+        // past Tc, and from there at most `past_max` steps on while M stays positive
+        while t_index <= steps || (t_index <= steps + config.past_max && self.mag() >= 0.) {
             // simulate
             self.n = 0;
-            loop {
-                self.time += 1;
-                self.n += 1;
-
-                let prev_state = StateSnapshot::of_simulation(&self);
-
-                self.mc_iter(rand);
-                if self.network.deg_mse != 0. && self.time.rem_euclid(201) == 0 {
-                    self.network.plot_spins(
+            let equilibration = equilibrate_with(self, MAX_SWEEPS, rand, |s, rng| engine.sweep(s, rng), |s, prev_state| {
+                if s.network.deg_mse != 0. && s.time.rem_euclid(201) == 0 {
+                    s.network.plot_spins(
                         &format!(
                             "view_size={}_avg={}.png",
-                            self.network.size, self.network.deg_avg
+                            s.network.size, s.network.deg_avg
                         ),
                         &format!(
                             "T: {}, t: {}, ΔH: {}, ΔM: {}, δM: {}",
-                            self.config.temp,
-                            self.time,
-                            (prev_state.ham() - self.ham()).abs(),
-                            (prev_state.mag - self.mag()).abs(),
-                            (self.free_count as f64 / self.network.size2)
+                            s.config.temp,
+                            s.time,
+                            (prev_state.ham - s.ham()).abs(),
+                            (prev_state.mag - s.mag()).abs(),
+                            (s.free_count as f64 / s.network.size2)
                         ),
                     )?;
                 }
 
-                if self.is_at_equilibrium(&prev_state) {
-                    break;
-                }
-            }
-
-            // update measurements
-            self.ham_internal = self.calc_h_internal();
-            self.ham_agr_internal = self.ham_internal;
+                Ok(())
+            })?;
+            save_equilibration(&mut equilibrium_writer, self.config.temp, equilibration)?;
 
-            self.ham_external = self.calc_h_external();
-            self.ham_agr_external = self.ham_external;
+            // sample
+            self.measure_with(&config.measure, rand, &mut engine);
 
-            self.calc_magnetisation();
+            // update measurements
+            engine.sync(self);
+            self.refresh_measurements();
 
             // save
             data_writer.serialize(self.snapshot_phase()?)?;
+            self.save_structure(&mut structure_writer, self.config.temp)?;
+            self.save_domains(&mut domains_writer, self.config.temp)?;
+            if let Some(writer) = series_writer.as_mut() {
+                self.save_series(writer, &config.measure)?;
+            }
+
+            if let (Some(c), Some(writer)) = (self.correlation.as_ref(), correlation_writer.as_mut()) {
+                for ((&r, &pairs), g) in c.radii.iter().zip(c.pairs.iter()).zip(c.function()) {
+                    writer.serialize((self.config.temp, r, pairs, g))?;
+                }
+                writer.flush()?;
+            }
 
             // plot frame
             frame!(
@@ -524,7 +1171,8 @@ impl Simulation {
             );
 
             // step
-            round!(self.config.temp, +, config.t_step);
+            t_index += 1;
+            self.set_temp(grid_point(config.t_min, t_index as i64, config.t_step));
             prev_time = self.time;
         }
 
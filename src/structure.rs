@@ -0,0 +1,154 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::network::Network;
+
+/// Static structure factor S(k) = |Σ_r s_r e^{-ik·r}|² / N of the regular
+/// periodic lattice, averaged over sampled configurations and binned by
+/// |k| in units of 2π/L.
+#[derive(Clone)]
+pub struct StructureFactor {
+    size: usize,
+    fft: Arc<dyn Fft<f64>>,
+    /// radial bin of every (kx, ky)
+    bins: Vec<usize>,
+    /// mean |k| of every bin
+    pub wavenumbers: Vec<f64>,
+    counts: Vec<f64>,
+    sums: Vec<f64>,
+    /// transform of the configuration pushed last, kx and ky swapped
+    transform: Vec<Complex<f64>>,
+    /// S at the four smallest nonzero wave vectors, summed
+    s_min: f64,
+    samples: f64,
+}
+
+impl std::fmt::Debug for StructureFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StructureFactor")
+            .field("size", &self.size)
+            .field("samples", &self.samples)
+            .finish()
+    }
+}
+
+impl StructureFactor {
+    pub fn new(size: usize) -> Self {
+        let l = size as i64;
+        let modes: Vec<f64> = (0..size * size)
+            .map(|i| {
+                let (nx, ny) = ((i % size) as i64, (i / size) as i64);
+                let (nx, ny) = (nx.min(l - nx), ny.min(l - ny));
+
+                ((nx * nx + ny * ny) as f64).sqrt()
+            })
+            .collect();
+
+        let bins: Vec<usize> = modes.iter().map(|n| n.round() as usize).collect();
+        let len = bins.iter().max().map_or(1, |b| b + 1);
+        let mut wavenumbers = vec![0f64; len];
+        let mut counts = vec![0f64; len];
+
+        for (&bin, &n) in bins.iter().zip(modes.iter()) {
+            wavenumbers[bin] += 2. * PI * n / size as f64;
+            counts[bin] += 1.;
+        }
+
+        for (k, &c) in wavenumbers.iter_mut().zip(counts.iter()) {
+            *k /= c.max(1.);
+        }
+
+        StructureFactor {
+            size,
+            fft: FftPlanner::new().plan_fft_forward(size),
+            bins,
+            wavenumbers,
+            sums: vec![0.; len],
+            counts,
+            transform: vec![],
+            s_min: 0.,
+            samples: 0.,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.sums.iter_mut().for_each(|x| *x = 0.);
+        self.s_min = 0.;
+        self.samples = 0.;
+    }
+
+    /// 2-D transform as rows then columns; the columns are done on the
+    /// transpose, which S(k) doesn't mind since |k| is symmetric.
+    pub fn push(&mut self, network: &Network) {
+        let l = self.size;
+        let mut rows: Vec<Complex<f64>> = network
+            .spins
+            .iter()
+            .map(|&s| Complex::new(s as f64, 0.))
+            .collect();
+
+        self.fft.process(&mut rows);
+
+        let mut columns: Vec<Complex<f64>> = (0..l * l).map(|i| rows[(i / l) + (i % l) * l]).collect();
+        self.fft.process(&mut columns);
+
+        let n = (l * l) as f64;
+        for (&bin, c) in self.bins.iter().zip(columns.iter()) {
+            self.sums[bin] += c.norm_sqr() / n;
+        }
+
+        if l > 1 {
+            self.s_min += [1, l - 1, l, (l - 1) * l]
+                .iter()
+                .map(|&i| columns[i].norm_sqr() / n)
+                .sum::<f64>();
+        }
+
+        self.transform = columns;
+        self.samples += 1.;
+    }
+
+    /// S of every wave vector of the configuration pushed last, in the
+    /// order of the transform.
+    pub fn power(&self) -> impl Iterator<Item = f64> + '_ {
+        let n = (self.size * self.size) as f64;
+
+        self.transform.iter().map(move |c| c.norm_sqr() / n)
+    }
+
+    /// Radially averaged S(k) for every bin.
+    pub fn function(&self) -> Vec<f64> {
+        let samples = self.samples.max(1.);
+
+        self.sums
+            .iter()
+            .zip(self.counts.iter())
+            .map(|(&s, &c)| s / (c.max(1.) * samples))
+            .collect()
+    }
+
+    /// Second moment length from the two smallest wave vectors,
+    /// ξ = sqrt(S(0) / S(k_min) - 1) / (2 sin(k_min / 2)).
+    pub fn second_moment_length(&self) -> f64 {
+        let samples = self.samples.max(1.);
+        let s_0 = self.sums[0] / samples;
+        let s_min = self.s_min / (4. * samples);
+        let k_min = 2. * PI / self.size as f64;
+
+        (s_0 / s_min - 1.).max(0.).sqrt() / (2. * (k_min / 2.).sin())
+    }
+
+    /// Characteristic domain size L = 2π / ⟨k⟩, with ⟨k⟩ the first moment of
+    /// S(k) over the nonzero wave vectors.
+    pub fn domain_size(&self) -> f64 {
+        let (num, den) = self
+            .wavenumbers
+            .iter()
+            .zip(self.sums.iter())
+            .skip(1)
+            .fold((0., 0.), |(num, den), (&k, &s)| (num + k * s, den + s));
+
+        2. * PI * den / num
+    }
+}
@@ -0,0 +1,164 @@
+use std::{error::Error, path::Path};
+
+use csv::Writer;
+use rand::Rng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    child::{send, ChildMsg},
+    simulation::{Accumulator, Simulation},
+};
+
+#[derive(Debug)]
+pub struct TemperingConfig {
+    pub t_min: f64,
+    pub t_max: f64,
+    pub replicas: usize,
+    pub eq_sweeps: usize,
+    pub sweeps: usize,
+    pub swap_interval: usize,
+}
+
+fn row(acc: &Accumulator, s: &Simulation) -> Vec<f64> {
+    vec![
+        s.config.temp,
+        acc.mag(),
+        acc.mag_abs(),
+        acc.energy(),
+        acc.heat(s),
+        acc.susceptibility(s),
+        acc.samples,
+    ]
+}
+
+#[derive(Debug)]
+pub struct Tempering {
+    pub replicas: Vec<Simulation>,
+    pub attempts: Vec<u64>,
+    pub accepted: Vec<u64>,
+}
+
+/// Geometric temperature ladder, denser at low temperatures where the
+/// energy distributions of neighbouring replicas are narrow.
+pub fn ladder(t_min: f64, t_max: f64, n: usize) -> Vec<f64> {
+    if n < 2 {
+        return vec![t_min];
+    }
+
+    let ratio = (t_max / t_min).powf(1. / (n - 1) as f64);
+
+    (0..n).map(|k| t_min * ratio.powi(k as i32)).collect()
+}
+
+impl Tempering {
+    pub fn new(base: &Simulation, config: &TemperingConfig, rand: &mut ChaCha20Rng) -> Self {
+        let replicas: Vec<Simulation> = ladder(config.t_min, config.t_max, config.replicas)
+            .into_iter()
+            .map(|temp| base.replica(temp, rand))
+            .collect();
+        let pairs = replicas.len().saturating_sub(1);
+
+        Tempering {
+            replicas,
+            attempts: vec![0; pairs],
+            accepted: vec![0; pairs],
+        }
+    }
+
+    fn attempt_swap(&mut self, i: usize, rand: &mut ChaCha20Rng) {
+        let (lo, hi) = self.replicas.split_at_mut(i + 1);
+        let (a, b) = (&mut lo[i], &mut hi[0]);
+
+        let d_beta = 1. / (a.config.kb * a.config.temp) - 1. / (b.config.kb * b.config.temp);
+        let log_p = d_beta * (a.ham() - b.ham());
+
+        self.attempts[i] += 1;
+
+        if log_p >= 0. || rand.gen::<f64>() < log_p.exp() {
+            a.swap_state(b);
+            self.accepted[i] += 1;
+        }
+    }
+
+    fn swap_rate(&self) -> f64 {
+        let attempts = self.attempts.iter().sum::<u64>().max(1);
+
+        self.accepted.iter().sum::<u64>() as f64 / attempts as f64
+    }
+
+    pub fn simulate(
+        &mut self,
+        data_dist_path: &Path,
+        swaps_dist_path: &Path,
+        config: &TemperingConfig,
+        rand: &mut ChaCha20Rng,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut acc = vec![Accumulator::default(); self.replicas.len()];
+        let total = config.eq_sweeps + config.sweeps;
+        let interval = config.swap_interval.max(1);
+
+        for sweep in 0..total {
+            for s in self.replicas.iter_mut() {
+                s.mc_iter(rand);
+                s.time += 1;
+            }
+
+            // alternate between even and odd pairs so every pair gets a chance
+            if sweep % interval == 0 {
+                let parity = (sweep / interval) % 2;
+
+                for i in (parity..self.attempts.len()).step_by(2) {
+                    self.attempt_swap(i, rand);
+                }
+            }
+
+            if sweep >= config.eq_sweeps {
+                for (a, s) in acc.iter_mut().zip(self.replicas.iter()) {
+                    a.push(s.mag(), s.ham());
+                }
+            }
+
+            if sweep % 100 == 0 {
+                let s = &self.replicas[0];
+
+                send!(
+                    s.tx,
+                    s.name,
+                    format!(
+                        "sweep: {}/{}, replicas: {}, swap rate: {:.3}",
+                        sweep,
+                        total,
+                        self.replicas.len(),
+                        self.swap_rate()
+                    )
+                );
+            }
+        }
+
+        let mut data_writer = Writer::from_path(data_dist_path)?;
+        data_writer.write_record(["T", "M", "absM", "E", "C", "X", "samples"])?;
+
+        for (a, s) in acc.iter().zip(self.replicas.iter()) {
+            data_writer.serialize(row(a, s))?;
+        }
+
+        data_writer.flush()?;
+
+        let mut swaps_writer = Writer::from_path(swaps_dist_path)?;
+        swaps_writer.write_record(["T1", "T2", "attempts", "accepted", "rate"])?;
+
+        for (i, (&attempts, &accepted)) in self.attempts.iter().zip(self.accepted.iter()).enumerate() {
+            swaps_writer.serialize((
+                self.replicas[i].config.temp,
+                self.replicas[i + 1].config.temp,
+                attempts,
+                accepted,
+                accepted as f64 / attempts.max(1) as f64,
+            ))?;
+        }
+
+        swaps_writer.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use std::{error::Error, path::Path};
+
+use csv::Writer;
+
+use crate::{
+    network::Network,
+    simulation::{grid_point, HysteresisConfig, PhaseConfig},
+};
+
+const TOLERANCE: f64 = 1e-10;
+const MAX_ITERATIONS: usize = 10_000;
+
+/// ln cosh(x) without overflowing at large |x|.
+fn ln_cosh(x: f64) -> f64 {
+    x.abs() + (-2. * x.abs()).exp().ln_1p() - 2f64.ln()
+}
+
+/// Heterogeneous mean field on the graph, m_i = tanh(β (J Σ_j m_j + H)),
+/// solved by Gauss–Seidel iteration from the previous solution.
+#[derive(Debug)]
+pub struct MeanField {
+    pub mags: Vec<f64>,
+}
+
+impl MeanField {
+    pub fn new(network: &Network) -> Self {
+        MeanField {
+            mags: vec![1.; network.size * network.size],
+        }
+    }
+
+    /// Returns the average magnetisation per spin.
+    pub fn solve(&mut self, network: &Network, beta: f64, h: f64, j: f64) -> f64 {
+        for _ in 0..MAX_ITERATIONS {
+            let mut change = 0f64;
+
+            for i in 0..self.mags.len() {
+                let field = j * network.lattice[i].iter().map(|&k| self.mags[k]).sum::<f64>() + h;
+                let m = (beta * field).tanh();
+
+                change = change.max((m - self.mags[i]).abs());
+                self.mags[i] = m;
+            }
+
+            if change < TOLERANCE {
+                break;
+            }
+        }
+
+        self.mags.iter().sum::<f64>() / self.mags.len() as f64
+    }
+}
+
+/// Bethe (cavity) approximation through belief propagation on the graph
+/// itself, so every site keeps its actual degree. `biases[e]` is the cavity
+/// field u_{i→k} sent along the directed edge e = (i, k), stored in the
+/// order of `lattice[i]`.
+#[derive(Debug)]
+pub struct Bethe {
+    offsets: Vec<usize>,
+    reverse: Vec<usize>,
+    pub biases: Vec<f64>,
+}
+
+impl Bethe {
+    pub fn new(network: &Network, j: f64) -> Self {
+        let n = network.size * network.size;
+        let mut offsets = vec![0; n + 1];
+
+        for i in 0..n {
+            offsets[i + 1] = offsets[i] + network.lattice[i].len();
+        }
+
+        // the k-th copy of a (possibly repeated) bond i–k pairs with the k-th copy of k–i
+        let reverse = (0..n)
+            .flat_map(|i| {
+                let ns = &network.lattice[i];
+
+                (0..ns.len()).map(move |slot| (i, ns, slot))
+            })
+            .map(|(i, ns, slot)| {
+                let k = ns[slot];
+                let copy = ns[..slot].iter().filter(|&&x| x == k).count();
+                let back = network.lattice[k]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &x)| x == i)
+                    .nth(copy)
+                    .map(|(s, _)| s)
+                    .unwrap();
+
+                offsets[k] + back
+            })
+            .collect();
+
+        Bethe {
+            biases: vec![j; offsets[n]],
+            offsets,
+            reverse,
+        }
+    }
+
+    fn incoming(&self, i: usize) -> f64 {
+        (self.offsets[i]..self.offsets[i + 1])
+            .map(|e| self.biases[self.reverse[e]])
+            .sum()
+    }
+
+    /// Returns the average magnetisation per spin.
+    pub fn solve(&mut self, beta: f64, h: f64, j: f64) -> f64 {
+        let n = self.offsets.len() - 1;
+
+        for _ in 0..MAX_ITERATIONS {
+            let mut change = 0f64;
+
+            for i in 0..n {
+                let total = h + self.incoming(i);
+
+                for e in self.offsets[i]..self.offsets[i + 1] {
+                    let cavity = total - self.biases[self.reverse[e]];
+                    // atanh(tanh(βJ) tanh(βh)) / β, in a form that stays finite as T → 0
+                    let u = (ln_cosh(beta * (cavity + j)) - ln_cosh(beta * (cavity - j))) / (2. * beta);
+
+                    change = change.max((u - self.biases[e]).abs());
+                    self.biases[e] = u;
+                }
+            }
+
+            if change < TOLERANCE {
+                break;
+            }
+        }
+
+        (0..n).map(|i| (beta * (h + self.incoming(i))).tanh()).sum::<f64>() / n as f64
+    }
+}
+
+/// Both approximations for one network, each continued from its previous
+/// solution so that sweeps follow a branch the way the simulation does.
+#[derive(Debug)]
+pub struct Theory<'a> {
+    network: &'a Network,
+    mean_field: MeanField,
+    bethe: Bethe,
+    j: f64,
+    kb: f64,
+}
+
+impl<'a> Theory<'a> {
+    pub fn new(network: &'a Network, j: f64, kb: f64) -> Self {
+        Theory {
+            network,
+            mean_field: MeanField::new(network),
+            bethe: Bethe::new(network, j),
+            j,
+            kb,
+        }
+    }
+
+    /// `(M_mf, M_bethe)` per spin at (T, H).
+    pub fn mag(&mut self, temp: f64, h: f64) -> (f64, f64) {
+        let beta = 1. / (self.kb * temp);
+
+        (
+            self.mean_field.solve(self.network, beta, h, self.j),
+            self.bethe.solve(beta, h, self.j),
+        )
+    }
+
+    /// M(T) at H = 0, heating from `t_min` to `t_max`.
+    pub fn save_phase(&mut self, path: &Path, config: &PhaseConfig) -> Result<(), Box<dyn Error>> {
+        let mut writer = Writer::from_path(path)?;
+        writer.write_record(["T", "H", "M_mf", "M_bethe"])?;
+
+        let steps = ((config.t_max - config.t_min) / config.t_step).round() as i64;
+
+        for t_index in 0..=steps {
+            let temp = grid_point(config.t_min, t_index, config.t_step);
+            let (m_mf, m_bethe) = self.mag(temp, 0.);
+
+            writer.serialize((temp, 0., m_mf, m_bethe))?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    /// M(H) at fixed T along the same field path as `simulate_hysteresis`,
+    /// from H = 0 up to `h_max`, down to `h_min` and back up.
+    pub fn save_hysteresis(
+        &mut self,
+        path: &Path,
+        temp: f64,
+        config: &HysteresisConfig,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut writer = Writer::from_path(path)?;
+        writer.write_record(["T", "H", "M_mf", "M_bethe"])?;
+
+        let h_index_max = (config.h_max / config.h_step).round() as i64;
+        let h_index_min = (config.h_min / config.h_step).round() as i64;
+        let mut h_index = 0i64;
+
+        let mut saw_max = false;
+        let mut step_direction = 1i64;
+
+        while !(h_index >= h_index_max && saw_max) {
+            let is_max = h_index >= h_index_max || h_index <= h_index_min;
+
+            if is_max {
+                step_direction *= -1;
+            }
+
+            saw_max |= is_max;
+
+            let h = grid_point(0., h_index, config.h_step);
+            let (m_mf, m_bethe) = self.mag(temp, h);
+
+            writer.serialize((temp, h, m_mf, m_bethe))?;
+
+            h_index += step_direction;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use std::{error::Error, path::Path, sync::mpsc::Sender};
+
+use csv::{Reader, Writer};
+use rand::Rng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::{
+    child::{send, ChildMsg},
+    matrix::pos_of_index,
+    network::Network,
+    simulation::grid_point,
+};
+
+#[derive(Debug)]
+pub struct WangLandauConfig {
+    pub j: f64,
+    pub ln_f_final: f64,
+    pub flatness: f64,
+    pub check_sweeps: usize,
+}
+
+#[derive(Debug)]
+pub struct ThermoConfig {
+    pub t_min: f64,
+    pub t_max: f64,
+    pub t_step: f64,
+    pub kb: f64,
+}
+
+/// Density of states over the energy levels that were visited, together with
+/// the microcanonical average of |M| at each level.
+#[derive(Debug, Clone)]
+pub struct DensityOfStates {
+    pub spins: f64,
+    pub energies: Vec<f64>,
+    pub ln_g: Vec<f64>,
+    pub mag_abs: Vec<f64>,
+}
+
+#[derive(Debug)]
+pub struct WangLandau {
+    pub network: Network,
+    pub config: WangLandauConfig,
+    pub name: String,
+    pub tx: Sender<ChildMsg>,
+    /// Σ s_i s_j over every bond, counted once
+    bond_sum: i64,
+    spin_sum: i64,
+    bonds: i64,
+    ln_g: Vec<f64>,
+    hist: Vec<u64>,
+    visited: Vec<bool>,
+    mag_sum: Vec<f64>,
+    mag_count: Vec<u64>,
+}
+
+/// Errs on settings under which `simulate` would never finish: no sweeps
+/// between flatness checks, or a flatness no histogram can reach.
+pub fn check_config(flatness: f64, check_sweeps: usize) -> Result<(), Box<dyn Error>> {
+    if check_sweeps == 0 {
+        return Err("--check-sweeps must be at least 1".into());
+    }
+    if !(flatness > 0. && flatness <= 1.) {
+        return Err(format!("--flatness must be in (0, 1], not {}", flatness).into());
+    }
+
+    Ok(())
+}
+
+fn log_sum_exp(xs: impl Iterator<Item = f64> + Clone) -> f64 {
+    let max = xs.clone().fold(f64::NEG_INFINITY, f64::max);
+
+    max + xs.map(|x| (x - max).exp()).sum::<f64>().ln()
+}
+
+impl WangLandau {
+    pub fn new(network: Network, config: WangLandauConfig, name: String, tx: Sender<ChildMsg>) -> Self {
+        let bonds = network.lattice.iter().map(|ns| ns.len()).sum::<usize>() as i64 / 2;
+        let levels = bonds as usize + 1;
+
+        let mut wl = WangLandau {
+            network,
+            config,
+            name,
+            tx,
+            bond_sum: 0,
+            spin_sum: 0,
+            bonds,
+            ln_g: vec![0.; levels],
+            hist: vec![0; levels],
+            visited: vec![false; levels],
+            mag_sum: vec![0.; levels],
+            mag_count: vec![0; levels],
+        };
+
+        wl.bond_sum = wl
+            .network
+            .spins
+            .enumerator()
+            .map(|(p, &s)| (s as i64) * wl.network.get_neighbours(p).iter().map(|&n| n as i64).sum::<i64>())
+            .sum::<i64>()
+            / 2;
+        wl.spin_sum = wl.network.spins.iter().map(|&s| s as i64).sum();
+
+        wl
+    }
+
+    fn level(&self, bond_sum: i64) -> usize {
+        ((bond_sum + self.bonds) / 2) as usize
+    }
+
+    fn step(&mut self, rand: &mut ChaCha20Rng, ln_f: f64) {
+        let i = rand.gen_range(0..self.network.size2 as usize);
+        let s = self.network.spins[i] as i64;
+        let new_bond_sum = self.bond_sum - 2 * s * self.network.local_field(pos_of_index(self.network.size, i));
+
+        let (old, new) = (self.level(self.bond_sum), self.level(new_bond_sum));
+        let log_p = self.ln_g[old] - self.ln_g[new];
+
+        if log_p >= 0. || rand.gen::<f64>() < log_p.exp() {
+            self.network.spins[i] *= -1;
+            self.bond_sum = new_bond_sum;
+            self.spin_sum -= 2 * s;
+        }
+
+        let cur = self.level(self.bond_sum);
+
+        self.ln_g[cur] += ln_f;
+        self.hist[cur] += 1;
+        self.visited[cur] = true;
+        self.mag_sum[cur] += (self.spin_sum as f64 / self.network.size2).abs();
+        self.mag_count[cur] += 1;
+    }
+
+    fn is_flat(&self) -> bool {
+        let hs: Vec<u64> = self
+            .hist
+            .iter()
+            .zip(self.visited.iter())
+            .filter(|(_, &v)| v)
+            .map(|(&h, _)| h)
+            .collect();
+
+        if hs.is_empty() {
+            return false;
+        }
+
+        let mean = hs.iter().sum::<u64>() as f64 / hs.len() as f64;
+        let min = *hs.iter().min().unwrap() as f64;
+
+        min >= self.config.flatness * mean
+    }
+
+    pub fn simulate(&mut self, rand: &mut ChaCha20Rng) -> DensityOfStates {
+        let sweep = self.network.size2 as usize;
+        let mut ln_f = 1f64;
+        let mut stage = 0;
+
+        while ln_f > self.config.ln_f_final {
+            let mut sweeps = 0;
+
+            loop {
+                for _ in 0..(self.config.check_sweeps * sweep) {
+                    self.step(rand, ln_f);
+                }
+
+                sweeps += self.config.check_sweeps;
+
+                if self.is_flat() {
+                    break;
+                }
+            }
+
+            send!(
+                self.tx,
+                self.name,
+                format!(
+                    "stage: {}, ln f: {:e}, sweeps: {}, levels: {}",
+                    stage,
+                    ln_f,
+                    sweeps,
+                    self.visited.iter().filter(|&&v| v).count()
+                )
+            );
+
+            ln_f /= 2.;
+            stage += 1;
+
+            // |M|(E) is only kept from the final, most converged stage
+            if ln_f > self.config.ln_f_final {
+                self.hist.iter_mut().for_each(|h| *h = 0);
+                self.mag_sum.iter_mut().for_each(|m| *m = 0.);
+                self.mag_count.iter_mut().for_each(|c| *c = 0);
+            }
+        }
+
+        self.density_of_states()
+    }
+
+    fn density_of_states(&self) -> DensityOfStates {
+        let levels: Vec<usize> = (0..self.ln_g.len()).filter(|&k| self.visited[k]).collect();
+        let spins = self.network.size2;
+
+        // normalise so that Σ g(E) = 2^N
+        let ln_total = log_sum_exp(levels.iter().map(|&k| self.ln_g[k]));
+        let shift = spins * 2f64.ln() - ln_total;
+
+        DensityOfStates {
+            spins,
+            energies: levels
+                .iter()
+                .map(|&k| -self.config.j * (2 * k as i64 - self.bonds) as f64)
+                .collect(),
+            ln_g: levels.iter().map(|&k| self.ln_g[k] + shift).collect(),
+            mag_abs: levels
+                .iter()
+                .map(|&k| self.mag_sum[k] / self.mag_count[k].max(1) as f64)
+                .collect(),
+        }
+    }
+}
+
+impl DensityOfStates {
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut writer = Writer::from_path(path)?;
+        writer.write_record(["E", "lng", "absM", "N"])?;
+
+        for k in 0..self.energies.len() {
+            writer.serialize((self.energies[k], self.ln_g[k], self.mag_abs[k], self.spins))?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut reader = Reader::from_path(path)?;
+        let mut dos = DensityOfStates {
+            spins: 0.,
+            energies: vec![],
+            ln_g: vec![],
+            mag_abs: vec![],
+        };
+
+        for row in reader.deserialize() {
+            let (e, ln_g, mag_abs, spins): (f64, f64, f64, f64) = row?;
+
+            dos.energies.push(e);
+            dos.ln_g.push(ln_g);
+            dos.mag_abs.push(mag_abs);
+            dos.spins = spins;
+        }
+
+        Ok(dos)
+    }
+
+    /// Canonical averages at `temp`; returns `[T, F, S, E, C, absM]` with the
+    /// free energy, entropy and specific heat given per spin.
+    pub fn thermodynamics(&self, temp: f64, kb: f64) -> Vec<f64> {
+        let beta = 1. / (kb * temp);
+        let weights = self
+            .energies
+            .iter()
+            .zip(self.ln_g.iter())
+            .map(|(&e, &g)| g - beta * e);
+        let ln_z = log_sum_exp(weights.clone());
+        let ps: Vec<f64> = weights.map(|w| (w - ln_z).exp()).collect();
+
+        let avg = |xs: &Vec<f64>, f: &dyn Fn(f64) -> f64| -> f64 {
+            ps.iter().zip(xs.iter()).map(|(p, &x)| p * f(x)).sum()
+        };
+        let e = avg(&self.energies, &|x| x);
+        let e2 = avg(&self.energies, &|x| x * x);
+        let mag_abs = avg(&self.mag_abs, &|x| x);
+
+        let f = -kb * temp * ln_z / self.spins;
+        let s = (e / self.spins - f) / temp;
+        let c = (e2 - e * e) / (kb * temp * temp * self.spins);
+
+        vec![temp, f, s, e, c, mag_abs]
+    }
+
+    pub fn save_thermodynamics(&self, path: &Path, config: &ThermoConfig) -> Result<(), Box<dyn Error>> {
+        let mut writer = Writer::from_path(path)?;
+        writer.write_record(["T", "F", "S", "E", "C", "absM"])?;
+
+        let steps = ((config.t_max - config.t_min) / config.t_step).round() as usize;
+
+        for k in 0..=steps {
+            let temp = grid_point(config.t_min, k as i64, config.t_step);
+
+            writer.serialize(self.thermodynamics(temp, config.kb))?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const ISING: &str = env!("CARGO_BIN_EXE_ising");
+
+const ARGS: [&str; 16] = [
+    "--size", "8", "--t-min", "1.5", "--t-max", "3.5", "--t-step", "1", "--seeds", "1", "--eq-steps", "100",
+    "--samples", "10000", "--criterion", "fixed",
+];
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ising-multispin-{}-{}", std::process::id(), name));
+    fs::remove_dir_all(&dir).unwrap_or(());
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+fn ising(cwd: &Path, command: &str) -> Command {
+    let mut c = Command::new(ISING);
+    c.current_dir(cwd).arg(command).args(ARGS).stdout(Stdio::null()).stderr(Stdio::null());
+
+    c
+}
+
+/// Wall time of a run with `args` in place of ARGS.
+fn timed(cwd: &Path, command: &str, args: &[&str]) -> Duration {
+    let start = Instant::now();
+    let status = Command::new(ISING)
+        .current_dir(cwd)
+        .arg(command)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    start.elapsed()
+}
+
+/// Rows of a phase data.csv, by column name.
+fn rows(path: &Path) -> Vec<HashMap<String, f64>> {
+    let data = fs::read_to_string(path).unwrap();
+    let mut lines = data.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+
+    lines
+        .map(|line| {
+            header
+                .iter()
+                .zip(line.split(','))
+                .map(|(&h, x)| (h.to_string(), x.parse().unwrap_or(f64::NAN)))
+                .collect()
+        })
+        .collect()
+}
+
+/// The multi-spin coded engine samples the same ensemble as the Metropolis
+/// sweeps of `Simulation`: ⟨|M|⟩ and ⟨E⟩ agree within their errors below,
+/// near and above Tc.
+#[test]
+fn multispin_matches_simulation() {
+    let dir = fresh_dir("phase");
+    let mut metropolis = ising(&dir, "phase").spawn().unwrap();
+    let mut multispin = ising(&dir, "msc").spawn().unwrap();
+    assert!(metropolis.wait().unwrap().success());
+    assert!(multispin.wait().unwrap().success());
+
+    let run = "size=8_step=1_max=3.5_seed=1/data.csv";
+    let metropolis = rows(&dir.join("data/regular/phase").join(run));
+    let multispin = rows(&dir.join("data/regular/msc").join(run));
+    assert_eq!(metropolis.len(), 3);
+    assert_eq!(multispin.len(), 3);
+
+    for (a, b) in metropolis.iter().zip(multispin.iter()) {
+        assert_eq!(a["T"], b["T"]);
+
+        for (column, error) in [("absM", "absM_err"), ("aE", "E_err")] {
+            let sigma = a[error].hypot(b[error]);
+
+            assert!(
+                (a[column] - b[column]).abs() < 4. * sigma,
+                "{} at T = {}: {} and {} ± {}",
+                column,
+                a["T"],
+                a[column],
+                b[column],
+                sigma
+            );
+        }
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// At a size where the sweeps outweigh everything else about a point, the
+/// multi-spin coded engine takes well under half the time of the Metropolis
+/// sweeps of `Simulation`, even unoptimised.
+#[test]
+fn multispin_outpaces_simulation() {
+    let dir = fresh_dir("pace");
+    let args = [
+        "--size", "128", "--t-min", "2.3", "--t-max", "2.3", "--t-step", "1", "--seeds", "1", "--eq-steps", "50",
+        "--samples", "5", "--interval", "10", "--criterion", "fixed", "--no-correlation", "--no-series",
+    ];
+
+    let metropolis = timed(&dir, "phase", &args);
+    let multispin = timed(&dir, "msc", &args);
+
+    assert!(2 * multispin < metropolis, "{:?} against {:?}", multispin, metropolis);
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,122 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+const ISING: &str = env!("CARGO_BIN_EXE_ising");
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ising-reference-{}-{}", std::process::id(), name));
+    fs::remove_dir_all(&dir).unwrap_or(());
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+fn ising(cwd: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(ISING);
+    command.current_dir(cwd).args(args).stdout(Stdio::null()).stderr(Stdio::null());
+
+    command
+}
+
+fn deviation(desc_path: &Path) -> serde_json::Value {
+    serde_json::from_str(&fs::read_to_string(desc_path.with_file_name("compare.json")).unwrap()).unwrap()
+}
+
+/// A run sitting exactly on the Onsager solution, at Tc (E = -√2 per spin)
+/// and at values from the closed forms, shows no deviation; its signed M,
+/// which a tunnelling finite lattice averages to 0, is not what is compared.
+#[test]
+fn onsager_run_has_no_deviation() {
+    let dir = fresh_dir("onsager");
+    let spins = 4.;
+    let tc = 2. / (1. + 2f64.sqrt()).ln();
+    let points = [
+        (1.5, 0.986499602621494, -1.951116573078377),
+        (2.0, 0.911319377877496, -1.745564575312554),
+        (tc, 0., -2f64.sqrt()),
+        (3.0, 0., -0.817309592502420),
+    ];
+
+    let mut data = String::from("T,M,absM,E\n");
+    for (t, m, e) in points {
+        data.push_str(&format!("{},{},{},{}\n", t, 0., m, e * spins));
+    }
+    fs::write(dir.join("data.csv"), data).unwrap();
+    fs::write(dir.join("desc.json"), r#"{"config":{"size":2}}"#).unwrap();
+
+    let desc = dir.join("desc.json");
+    assert!(ising(&dir, &["compare", desc.to_str().unwrap()]).status().unwrap().success());
+
+    let dev = deviation(&desc);
+    assert_eq!(dev["points"], 4);
+    assert_eq!(dev["mag_points"], 4);
+    assert!(dev["max_mag"].as_f64().unwrap() < 1e-9, "{}", dev);
+    assert!(dev["max_energy"].as_f64().unwrap() < 1e-9, "{}", dev);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// The width-4 transfer matrix describes a 4×∞ strip, which differs from the
+/// exactly enumerated 4×4 torus only by the loops wrapping around its length,
+/// O(tanh(β)³) in the energy per spin: the two agree ever closer as T grows.
+#[test]
+fn strip_matches_exact_enumeration() {
+    let dir = fresh_dir("strip");
+    let exact = [
+        "exact", "--size", "4", "--t-min", "8", "--t-max", "20", "--t-step", "4", "--seeds", "1",
+    ];
+    assert!(ising(&dir, &exact).status().unwrap().success());
+
+    let desc = dir.join("data/regular/exact/size=4_seed=1/desc.json");
+    assert!(ising(&dir, &["compare", "--strip", "4", desc.to_str().unwrap()]).status().unwrap().success());
+
+    // a strip has no magnetisation at H = 0 to hold M against
+    let dev = deviation(&desc);
+    assert_eq!(dev["points"], 4);
+    assert_eq!(dev["mag_points"], 0);
+
+    let compare = fs::read_to_string(desc.with_file_name("compare.csv")).unwrap();
+    for line in compare.lines().skip(1) {
+        let row: Vec<f64> = line.split(',').map(|x| x.parse().unwrap()).collect();
+        let (t, e, e_ref) = (row[0], row[4], row[5]);
+
+        assert!((e - e_ref).abs() < 2. * (1. / t).tanh().powi(3), "{}", line);
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// In a field M keeps its sign: a branch stuck against the field is off by
+/// about twice the saturated magnetisation, not on the reference.
+#[test]
+fn wrong_sign_branch_deviates() {
+    let dir = fresh_dir("branch");
+    fs::write(dir.join("data.csv"), "H,M,aM,absM,E\n1,-1,-0.99,0.99,-24\n").unwrap();
+    fs::write(dir.join("desc.json"), r#"{"config":{"size":2},"temp":1}"#).unwrap();
+
+    let desc = dir.join("desc.json");
+    assert!(ising(&dir, &["compare", "--strip", "2", desc.to_str().unwrap()]).status().unwrap().success());
+
+    let dev = deviation(&desc);
+    assert_eq!(dev["mag_points"], 1);
+    assert!(dev["max_mag"].as_f64().unwrap() > 1.9, "{}", dev);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// A strip too wide for the transfer matrix is an error, not a panic.
+#[test]
+fn too_wide_strip_is_an_error() {
+    let dir = fresh_dir("wide");
+    fs::write(dir.join("data.csv"), "T,absM,E\n1,1,-4\n").unwrap();
+    fs::write(dir.join("desc.json"), r#"{"config":{"size":2}}"#).unwrap();
+
+    let desc = dir.join("desc.json");
+    let status = ising(&dir, &["compare", "--strip", "17", desc.to_str().unwrap()]).status().unwrap();
+    assert_eq!(status.code(), Some(1));
+
+    fs::remove_dir_all(dir).unwrap();
+}
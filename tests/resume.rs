@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+const ISING: &str = env!("CARGO_BIN_EXE_ising");
+
+const HYS: [&str; 17] = [
+    "hys", "--size", "8", "--temps", "1.5", "--h-max", "0.6", "--h-step", "0.1", "--eq-steps", "10",
+    "--seeds", "3", "--samples", "20", "--checkpoint-every", "2",
+];
+
+const RUN: &str = "data/regular/hys/size=8_step=0.1_max=0.6_temp=1.5_seed=3";
+
+const FILES: [&str; 3] = ["data.csv", "series.csv", "equilibrium.csv"];
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ising-resume-{}-{}", std::process::id(), name));
+    fs::remove_dir_all(&dir).unwrap_or(());
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+fn ising(cwd: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(ISING);
+    command.current_dir(cwd).args(args).stdout(Stdio::null()).stderr(Stdio::null());
+
+    command
+}
+
+/// Whether `series.csv` of the run in `dir` holds more than its checkpoint.
+fn past_checkpoint(dir: &Path) -> bool {
+    let checkpoint: serde_json::Value = match fs::read_to_string(dir.join("checkpoint.json")) {
+        Ok(json) => serde_json::from_str(&json).unwrap(),
+        Err(_) => return false,
+    };
+    let offset = checkpoint["offsets"]["series.csv"].as_u64().unwrap();
+
+    fs::metadata(dir.join("series.csv")).unwrap().len() > offset
+}
+
+/// A hysteresis run killed after a checkpoint and resumed from another
+/// directory writes the same files, byte for byte, as one run straight through.
+#[test]
+fn resumed_hysteresis_matches_straight_run() {
+    let straight = fresh_dir("straight");
+    assert!(ising(&straight, &HYS).status().unwrap().success());
+
+    let stopped = fresh_dir("stopped");
+    let run = stopped.join(RUN);
+    let mut child = ising(&stopped, &HYS).spawn().unwrap();
+
+    // killed once a point past the checkpoint is in series.csv, which the
+    // resumed run has to cut off again
+    while !past_checkpoint(&run) {
+        thread::sleep(Duration::from_millis(1));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(!run.join("desc.json").exists(), "the run finished before it was killed");
+    assert!(past_checkpoint(&run));
+
+    let resume = ["resume", run.to_str().unwrap()];
+    assert!(ising(&std::env::temp_dir(), &resume).status().unwrap().success());
+
+    for file in FILES {
+        assert!(
+            fs::read(straight.join(RUN).join(file)).unwrap() == fs::read(run.join(file)).unwrap(),
+            "{} differs",
+            file
+        );
+    }
+
+    fs::remove_dir_all(straight).unwrap();
+    fs::remove_dir_all(stopped).unwrap();
+}